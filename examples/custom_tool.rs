@@ -0,0 +1,100 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use bevy_editor_pls::{
+    default_windows::cameras::{camera_2d_panzoom::PanCamControls, EditorCamera},
+    egui, EditorPlugin,
+};
+use bevy_tilemap_editor_pls::{
+    AddTilemapEditorTool, EditorError, TilemapEditorPlugin, Tool, ToolContext,
+};
+
+#[derive(Default, Component, Clone, Copy, Reflect)]
+#[reflect(Component)]
+struct SpawnPoint;
+
+/// Puts a spawn point into the middle of the clicked tile
+#[derive(Debug, Default)]
+struct SpawnPointPlacer;
+
+impl Tool for SpawnPointPlacer {
+    fn name(&self) -> &'static str {
+        "Spawn point"
+    }
+
+    fn icon(&self) -> Option<&'static str> {
+        Some("⚑")
+    }
+
+    fn viewport_ui(
+        &mut self,
+        ctx: &mut ToolContext,
+        hovered_tile: TilePos,
+        ui: &mut egui::Ui,
+        painter: &egui::Painter,
+    ) -> Result<(), EditorError> {
+        painter.add(egui::Shape::closed_line(
+            ctx.tile_outline(hovered_tile),
+            egui::Stroke::new(1.0, egui::Color32::GREEN),
+        ));
+
+        if !ui.input(|x| x.pointer.button_clicked(egui::PointerButton::Primary)) {
+            return Ok(());
+        }
+
+        let tilemap = ctx.world().entity(ctx.tilemap_entity());
+        let (Some(grid_size), Some(map_type), Some(transform)) = (
+            tilemap.get::<TilemapGridSize>(),
+            tilemap.get::<TilemapType>(),
+            tilemap.get::<GlobalTransform>(),
+        ) else {
+            return Ok(());
+        };
+        let translation = transform.transform_point(
+            hovered_tile
+                .center_in_world(grid_size, map_type)
+                .extend(1.0),
+        );
+
+        ctx.world_mut().spawn((
+            SpawnPoint,
+            Name::new("Spawn point"),
+            SpatialBundle::from_transform(Transform::from_translation(translation)),
+        ));
+
+        Ok(())
+    }
+}
+
+fn startup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut controls: Query<&mut PanCamControls, With<EditorCamera>>,
+) {
+    controls.single_mut().grab_buttons = vec![MouseButton::Middle];
+
+    let texture_handle: Handle<Image> = asset_server.load("tiles.png");
+    let map_size = TilemapSize { x: 32, y: 32 };
+    let tile_size = TilemapTileSize { x: 16.0, y: 16.0 };
+
+    commands.insert_resource(ClearColor(Color::BLACK));
+    commands.spawn(TilemapBundle {
+        grid_size: tile_size.into(),
+        size: map_size,
+        storage: TileStorage::empty(map_size),
+        texture: TilemapTexture::Single(texture_handle),
+        tile_size,
+        ..Default::default()
+    });
+}
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins.set(ImagePlugin::default_nearest()))
+        .add_plugin(TilemapPlugin)
+        .add_plugin(EditorPlugin::default())
+        .add_plugin(TilemapEditorPlugin)
+        .add_tilemap_editor_tool::<SpawnPointPlacer>()
+        .register_type::<SpawnPoint>()
+        .add_startup_system(startup)
+        .run()
+}
@@ -31,15 +31,105 @@ pub fn int_tile_pos_to_id(tile: UVec2, size: UVec2) -> Option<u32> {
     Some(tile.x + tile.y * size.x)
 }
 
-pub fn tile_id_to_pos(id: u32, atlas_size: egui::Vec2, tile_size: egui::Vec2) -> egui::Pos2 {
-    let tiles_per_line = (atlas_size.x / tile_size.x) as u32;
+/// Describes where the tiles are on an atlas image. The tiles are
+/// `spacing` apart from each other and `margin` away from the edges.
+#[derive(Clone, Copy, Debug)]
+pub struct AtlasLayout {
+    pub atlas_size: egui::Vec2,
+    pub tile_size: egui::Vec2,
+    pub spacing: egui::Vec2,
+    pub margin: egui::Vec2,
+}
+
+impl AtlasLayout {
+    fn stride(&self) -> egui::Vec2 {
+        self.tile_size + self.spacing
+    }
+
+    pub fn size_in_tiles(&self) -> UVec2 {
+        // The last tile in a row or a column doesn't have spacing after it
+        let stride = self.stride();
+        let usable = self.atlas_size - 2.0 * self.margin + self.spacing;
+
+        UVec2::new(
+            (usable.x / stride.x).max(0.0) as u32,
+            (usable.y / stride.y).max(0.0) as u32,
+        )
+    }
+
+    /// The top-left corner of the tile
+    pub fn tile_id_to_pos(&self, id: u32) -> egui::Pos2 {
+        let tiles_per_line = self.size_in_tiles().x.max(1);
+
+        self.int_tile_pos_to_pos(UVec2::new(id % tiles_per_line, id / tiles_per_line))
+    }
+
+    pub fn int_tile_pos_to_pos(&self, tile: UVec2) -> egui::Pos2 {
+        let stride = self.stride();
+
+        egui::pos2(
+            self.margin.x + tile.x as f32 * stride.x,
+            self.margin.y + tile.y as f32 * stride.y,
+        )
+    }
+
+    /// The tile under `pos`. Nothing is returned if `pos` is
+    /// in the margin or between the tiles.
+    pub fn pos_to_int_tile_pos(&self, pos: egui::Pos2) -> Option<UVec2> {
+        let pos = pos - self.margin;
+
+        if pos.x < 0.0 || pos.y < 0.0 {
+            return None;
+        }
+
+        let tile = gridify_int(pos, self.stride());
+        let offset = pos - self.int_tile_pos_to_pos(tile) + self.margin;
+
+        if offset.x >= self.tile_size.x || offset.y >= self.tile_size.y {
+            return None;
+        }
 
-    egui::pos2(
-        (id % tiles_per_line) as f32 * tile_size.x,
-        (id / tiles_per_line) as f32 * tile_size.y,
-    )
+        int_tile_pos_to_id(tile, self.size_in_tiles()).map(|_| tile)
+    }
 }
 
 pub fn bevy_to_egui(v: Vec2) -> egui::Vec2 {
     egui::vec2(v.x, v.y)
 }
+
+// One half of the Andrew's monotone chain
+fn half_hull<'a>(points: impl Iterator<Item = &'a Vec2>) -> Vec<Vec2> {
+    let mut hull = Vec::<Vec2>::new();
+
+    for &p in points {
+        while let [.., a, b] = *hull.as_slice() {
+            if (b - a).perp_dot(p - a) > 0.0 {
+                break;
+            }
+
+            hull.pop();
+        }
+
+        hull.push(p);
+    }
+
+    // The last point starts the other half
+    hull.pop();
+
+    hull
+}
+
+/// The convex hull of the points, going around counterclockwise.
+pub fn convex_hull(mut points: Vec<Vec2>) -> Vec<Vec2> {
+    points.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    points.dedup();
+
+    if points.len() < 3 {
+        return points;
+    }
+
+    let mut hull = half_hull(points.iter());
+    hull.extend(half_hull(points.iter().rev()));
+
+    hull
+}
@@ -1,5 +1,6 @@
 use bevy::ecs::query::QueryEntityError;
 use bevy::prelude::*;
+use bevy_ecs_tilemap::map::TilemapSize;
 use bevy_ecs_tilemap::tiles::TilePos;
 use thiserror::Error;
 
@@ -7,6 +8,8 @@ use thiserror::Error;
 pub enum EditorError {
     #[error("Tilemap texture type {0:?} isn't supported yet")]
     UnsupportedTilemapTextureType(&'static str),
+    #[error("The tileset has no tile with the texture index {0}")]
+    BadTileTextureIndex(u32),
     #[error("Encountered an incorrect image handle: {handle:?}")]
     InvalidImageHandle { handle: Handle<Image> },
     #[error("The tilemap entity {tilemap_entity:?} doesn't exist or is missing some important components")]
@@ -23,8 +26,29 @@ pub enum EditorError {
         #[source]
         query_error: QueryEntityError,
     },
+    #[error("The history step doesn't fit the tilemap {tilemap_entity:?} anymore, it must have been changed outside of the editor")]
+    StaleHistoryStep { tilemap_entity: Entity },
     #[error("The type {ty_name:?} doesn't implement `ReflectComponent`")]
     TypeNotReflectComponent { ty_name: &'static str },
     #[error("The type {ty_name:?} isn't registered")]
     TypeNotRegistered { ty_name: &'static str },
+    #[error(
+        "The component type {0:?} either isn't registered or doesn't implement `ReflectComponent`"
+    )]
+    UnknownComponentType(String),
+    #[error("The stored tilemap has no tiles, its size is {0:?}")]
+    EmptyTilemap(TilemapSize),
+    #[error("The stored tilemap has more than one tile at {0:?}")]
+    DuplicateTile(TilePos),
+    #[error("No tilemap format has been registered")]
+    NoTilemapFormats,
+    #[error("Failed to access the file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to write RON: {0}")]
+    RonSerialization(#[from] ron::Error),
+    #[error("Failed to read RON: {0}")]
+    RonDeserialization(#[from] ron::error::SpannedError),
+    /// For the errors of the user-provided [`crate::TilemapFormat`]s
+    #[error("The tilemap format has failed: {0}")]
+    Format(#[source] Box<dyn std::error::Error + Send + Sync>),
 }
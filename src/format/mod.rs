@@ -0,0 +1,267 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Write};
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use bevy::reflect::TypeRegistryInternal;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::queries::{TilePropertyQuery, TilemapQuery};
+use crate::tile_data::TileData;
+use crate::EditorError;
+
+mod ron_format;
+
+pub use ron_format::RonFormat;
+
+/// A file format the editor can save tilemaps into and load them from.
+/// Formats get registered through [`crate::AddTilemapEditorFormat`].
+///
+/// The editor takes care of moving the tiles between the world and [`TilemapData`],
+/// so a format only has to encode and decode it. The type registry is provided
+/// for the formats that want to store the tile data components.
+pub trait TilemapFormat: Send + Sync + 'static {
+    /// The name of the format, as shown in the editor.
+    fn name(&self) -> &str;
+
+    /// The extension of the files, without the leading dot.
+    fn extension(&self) -> &str;
+
+    fn write(
+        &self,
+        data: &TilemapData,
+        registry: &TypeRegistryInternal,
+        writer: &mut dyn Write,
+    ) -> Result<(), EditorError>;
+
+    fn read(
+        &self,
+        registry: &TypeRegistryInternal,
+        reader: &mut dyn Read,
+    ) -> Result<TilemapData, EditorError>;
+}
+
+#[derive(Clone, Resource)]
+pub(crate) struct TilemapFormats(pub Vec<Arc<dyn TilemapFormat>>);
+
+impl Default for TilemapFormats {
+    fn default() -> Self {
+        Self(vec![Arc::new(RonFormat)])
+    }
+}
+
+/// Where the tilemap's images come from. The paths are asset paths,
+/// so they can be fed right into the `AssetServer`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TextureSource {
+    Single(String),
+    Vector(Vec<String>),
+    TextureContainer(String),
+}
+
+impl TextureSource {
+    fn from_texture(texture: &TilemapTexture, asset_server: &AssetServer) -> Option<Self> {
+        let path = |handle: &Handle<Image>| {
+            asset_server
+                .get_handle_path(handle)
+                .map(|x| x.path().to_string_lossy().into_owned())
+        };
+
+        match texture {
+            TilemapTexture::Single(x) => path(x).map(Self::Single),
+            TilemapTexture::Vector(xs) => xs
+                .iter()
+                .map(path)
+                .collect::<Option<Vec<_>>>()
+                .map(Self::Vector),
+            TilemapTexture::TextureContainer(x) => path(x).map(Self::TextureContainer),
+        }
+    }
+
+    pub fn load(&self, asset_server: &AssetServer) -> TilemapTexture {
+        match self {
+            Self::Single(x) => TilemapTexture::Single(asset_server.load(x.as_str())),
+            Self::Vector(xs) => {
+                TilemapTexture::Vector(xs.iter().map(|x| asset_server.load(x.as_str())).collect())
+            }
+            Self::TextureContainer(x) => {
+                TilemapTexture::TextureContainer(asset_server.load(x.as_str()))
+            }
+        }
+    }
+}
+
+/// A single tile of [`TilemapData`].
+#[derive(Debug)]
+pub struct TileRecord {
+    pub pos: TilePos,
+    pub texture: TileTextureIndex,
+    pub flip: TileFlip,
+    pub color: TileColor,
+    /// The tile data components the tile had. See [`crate::EditorTileDataRegistry`].
+    pub components: Vec<Box<dyn Reflect>>,
+}
+
+/// A tilemap detached from the ECS world.
+#[derive(Debug)]
+pub struct TilemapData {
+    pub size: TilemapSize,
+    pub tile_size: TilemapTileSize,
+    pub grid_size: TilemapGridSize,
+    pub texture: Option<TextureSource>,
+    pub tiles: Vec<TileRecord>,
+}
+
+impl TilemapData {
+    pub(crate) fn extract(
+        world: &World,
+        tilemap_entity: Entity,
+        tilemap_query: &mut QueryState<TilemapQuery>,
+        tile_query: &mut QueryState<TilePropertyQuery>,
+        tile_data: &HashMap<u32, TileData>,
+    ) -> Result<Self, EditorError> {
+        let tilemap = tilemap_query
+            .get_manual(world, tilemap_entity)
+            .map_err(|query_error| EditorError::BadTilemapEntity {
+                tilemap_entity,
+                query_error,
+            })?;
+        let mut tiles = Vec::new();
+
+        for y in 0..tilemap.size.y {
+            for x in 0..tilemap.size.x {
+                let pos = TilePos { x, y };
+                let Some(tile_entity) = tilemap.storage.get(&pos) else { continue; };
+                let props = tile_query
+                    .get_manual(world, tile_entity)
+                    .map_err(|query_error| EditorError::BadTileEntity {
+                        tilemap_entity,
+                        tile_pos: pos,
+                        tile_entity,
+                        query_error,
+                    })?;
+                let components = tile_data
+                    .get(&props.texture.0)
+                    .map(|data| data.capture(world.entity(tile_entity)))
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|(_, value)| value)
+                    .collect();
+
+                tiles.push(TileRecord {
+                    pos,
+                    texture: *props.texture,
+                    flip: *props.flip,
+                    color: *props.color,
+                    components,
+                });
+            }
+        }
+
+        Ok(Self {
+            size: *tilemap.size,
+            tile_size: *tilemap.tile_size,
+            grid_size: *tilemap.grid_size,
+            texture: TextureSource::from_texture(tilemap.texture, world.resource::<AssetServer>()),
+            tiles,
+        })
+    }
+
+    /// Replaces the contents of the tilemap with the stored ones.
+    pub(crate) fn write_into(
+        &self,
+        world: &mut World,
+        tilemap_entity: Entity,
+        tilemap_query: &mut QueryState<TilemapQuery>,
+        registry: &TypeRegistryInternal,
+    ) -> Result<(), EditorError> {
+        // Check the tiles and resolve the component types first, so that a bad file
+        // doesn't leave the tilemap half-loaded
+        if self.size.x == 0 || self.size.y == 0 {
+            return Err(EditorError::EmptyTilemap(self.size));
+        }
+
+        let mut seen = HashSet::new();
+
+        for tile in self
+            .tiles
+            .iter()
+            .filter(|tile| tile.pos.within_map_bounds(&self.size))
+        {
+            if !seen.insert(tile.pos) {
+                return Err(EditorError::DuplicateTile(tile.pos));
+            }
+        }
+
+        let tile_components = self
+            .tiles
+            .iter()
+            .map(|tile| {
+                tile.components
+                    .iter()
+                    .map(|component| {
+                        let ty_name = component.type_name();
+
+                        registry
+                            .get_with_name(ty_name)
+                            .and_then(|x| x.data::<ReflectComponent>())
+                            .map(|reflect_component| (reflect_component, component.as_ref()))
+                            .ok_or_else(|| EditorError::UnknownComponentType(ty_name.to_owned()))
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let old_tiles = tilemap_query
+            .get(world, tilemap_entity)
+            .map_err(|query_error| EditorError::BadTilemapEntity {
+                tilemap_entity,
+                query_error,
+            })?
+            .storage
+            .iter()
+            .flatten()
+            .copied()
+            .collect::<Vec<_>>();
+
+        for tile_entity in old_tiles {
+            if let Some(tile_entity_mut) = world.get_entity_mut(tile_entity) {
+                tile_entity_mut.despawn_recursive();
+            }
+        }
+
+        let mut storage = TileStorage::empty(self.size);
+
+        for (tile, components) in self
+            .tiles
+            .iter()
+            .zip(tile_components)
+            .filter(|(tile, _)| tile.pos.within_map_bounds(&self.size))
+        {
+            let mut tile_entity_mut = world.spawn(TileBundle {
+                tilemap_id: TilemapId(tilemap_entity),
+                position: tile.pos,
+                texture_index: tile.texture,
+                flip: tile.flip,
+                color: tile.color,
+                ..default()
+            });
+
+            components
+                .into_iter()
+                .for_each(|(reflect_component, component)| {
+                    reflect_component.insert(&mut tile_entity_mut, component)
+                });
+
+            storage.set(&tile.pos, tile_entity_mut.id());
+        }
+
+        world.entity_mut(tilemap_entity).insert((
+            storage,
+            self.size,
+            self.tile_size,
+            self.grid_size,
+        ));
+
+        Ok(())
+    }
+}
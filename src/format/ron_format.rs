@@ -0,0 +1,484 @@
+use std::fmt;
+use std::io::{Read, Write};
+
+use bevy::prelude::*;
+use bevy::reflect::serde::{ReflectSerializer, UntypedReflectDeserializer};
+use bevy::reflect::TypeRegistryInternal;
+use bevy_ecs_tilemap::prelude::*;
+use serde::de::{DeserializeSeed, Error as _, MapAccess, SeqAccess, Visitor};
+use serde::ser::{SerializeSeq, SerializeStruct};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{TextureSource, TileRecord, TilemapData, TilemapFormat};
+use crate::EditorError;
+
+/// Stores tilemaps as human-readable [RON](https://github.com/ron-rs/ron) files.
+/// The tile data components are written with bevy's reflection, so they
+/// must be registered in the app's type registry.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RonFormat;
+
+impl TilemapFormat for RonFormat {
+    fn name(&self) -> &str {
+        "RON"
+    }
+
+    fn extension(&self) -> &str {
+        "ron"
+    }
+
+    fn write(
+        &self,
+        data: &TilemapData,
+        registry: &TypeRegistryInternal,
+        writer: &mut dyn Write,
+    ) -> Result<(), EditorError> {
+        ron::ser::to_writer_pretty(
+            writer,
+            &TilemapSer { data, registry },
+            // One tile per line
+            ron::ser::PrettyConfig::default().depth_limit(2),
+        )?;
+
+        Ok(())
+    }
+
+    fn read(
+        &self,
+        registry: &TypeRegistryInternal,
+        reader: &mut dyn Read,
+    ) -> Result<TilemapData, EditorError> {
+        let data = ron::Options::default().from_reader_seed(reader, TilemapDe { registry })?;
+
+        Ok(data)
+    }
+}
+
+// Plain mirrors of the tilemap components, since those don't implement serde's traits
+
+#[derive(Serialize, Deserialize)]
+struct UVec2Def {
+    x: u32,
+    y: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Vec2Def {
+    x: f32,
+    y: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct FlipDef {
+    x: bool,
+    y: bool,
+    d: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+enum TextureDef {
+    Single(String),
+    Vector(Vec<String>),
+    TextureContainer(String),
+}
+
+impl From<&TextureSource> for TextureDef {
+    fn from(value: &TextureSource) -> Self {
+        match value {
+            TextureSource::Single(x) => Self::Single(x.clone()),
+            TextureSource::Vector(x) => Self::Vector(x.clone()),
+            TextureSource::TextureContainer(x) => Self::TextureContainer(x.clone()),
+        }
+    }
+}
+
+impl From<TextureDef> for TextureSource {
+    fn from(value: TextureDef) -> Self {
+        match value {
+            TextureDef::Single(x) => Self::Single(x),
+            TextureDef::Vector(x) => Self::Vector(x),
+            TextureDef::TextureContainer(x) => Self::TextureContainer(x),
+        }
+    }
+}
+
+const TILEMAP_FIELDS: &[&str] = &["size", "tile_size", "grid_size", "texture", "tiles"];
+const TILE_FIELDS: &[&str] = &["pos", "texture", "flip", "color", "components"];
+
+#[derive(Deserialize)]
+#[serde(field_identifier, rename_all = "snake_case")]
+enum TilemapField {
+    Size,
+    TileSize,
+    GridSize,
+    Texture,
+    Tiles,
+}
+
+#[derive(Deserialize)]
+#[serde(field_identifier, rename_all = "snake_case")]
+enum TileField {
+    Pos,
+    Texture,
+    Flip,
+    Color,
+    Components,
+}
+
+struct TilemapSer<'a> {
+    data: &'a TilemapData,
+    registry: &'a TypeRegistryInternal,
+}
+
+impl<'a> Serialize for TilemapSer<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let data = self.data;
+        let mut state = serializer.serialize_struct("Tilemap", TILEMAP_FIELDS.len())?;
+
+        state.serialize_field(
+            "size",
+            &UVec2Def {
+                x: data.size.x,
+                y: data.size.y,
+            },
+        )?;
+        state.serialize_field(
+            "tile_size",
+            &Vec2Def {
+                x: data.tile_size.x,
+                y: data.tile_size.y,
+            },
+        )?;
+        state.serialize_field(
+            "grid_size",
+            &Vec2Def {
+                x: data.grid_size.x,
+                y: data.grid_size.y,
+            },
+        )?;
+        state.serialize_field("texture", &data.texture.as_ref().map(TextureDef::from))?;
+        state.serialize_field(
+            "tiles",
+            &TilesSer {
+                tiles: &data.tiles,
+                registry: self.registry,
+            },
+        )?;
+
+        state.end()
+    }
+}
+
+struct TilesSer<'a> {
+    tiles: &'a [TileRecord],
+    registry: &'a TypeRegistryInternal,
+}
+
+impl<'a> Serialize for TilesSer<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_seq(Some(self.tiles.len()))?;
+
+        for tile in self.tiles {
+            state.serialize_element(&TileSer {
+                tile,
+                registry: self.registry,
+            })?;
+        }
+
+        state.end()
+    }
+}
+
+struct TileSer<'a> {
+    tile: &'a TileRecord,
+    registry: &'a TypeRegistryInternal,
+}
+
+impl<'a> Serialize for TileSer<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let tile = self.tile;
+        let mut state = serializer.serialize_struct("Tile", TILE_FIELDS.len())?;
+
+        state.serialize_field(
+            "pos",
+            &UVec2Def {
+                x: tile.pos.x,
+                y: tile.pos.y,
+            },
+        )?;
+        state.serialize_field("texture", &tile.texture.0)?;
+        state.serialize_field(
+            "flip",
+            &FlipDef {
+                x: tile.flip.x,
+                y: tile.flip.y,
+                d: tile.flip.d,
+            },
+        )?;
+        state.serialize_field("color", &tile.color.0)?;
+        state.serialize_field(
+            "components",
+            &ComponentsSer {
+                components: &tile.components,
+                registry: self.registry,
+            },
+        )?;
+
+        state.end()
+    }
+}
+
+struct ComponentsSer<'a> {
+    components: &'a [Box<dyn Reflect>],
+    registry: &'a TypeRegistryInternal,
+}
+
+impl<'a> Serialize for ComponentsSer<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_seq(Some(self.components.len()))?;
+
+        for component in self.components {
+            state.serialize_element(&ReflectSerializer::new(component.as_ref(), self.registry))?;
+        }
+
+        state.end()
+    }
+}
+
+struct TilemapDe<'a> {
+    registry: &'a TypeRegistryInternal,
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for TilemapDe<'a> {
+    type Value = TilemapData;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_struct("Tilemap", TILEMAP_FIELDS, self)
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for TilemapDe<'a> {
+    type Value = TilemapData;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a tilemap")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut size = None;
+        let mut tile_size = None;
+        let mut grid_size = None;
+        let mut texture = None;
+        let mut tiles = None;
+
+        while let Some(field) = map.next_key()? {
+            match field {
+                TilemapField::Size => {
+                    let UVec2Def { x, y } = map.next_value()?;
+                    size = Some(TilemapSize { x, y });
+                }
+                TilemapField::TileSize => {
+                    let Vec2Def { x, y } = map.next_value()?;
+                    tile_size = Some(TilemapTileSize { x, y });
+                }
+                TilemapField::GridSize => {
+                    let Vec2Def { x, y } = map.next_value()?;
+                    grid_size = Some(TilemapGridSize { x, y });
+                }
+                TilemapField::Texture => {
+                    let value: Option<TextureDef> = map.next_value()?;
+                    texture = Some(value.map(TextureSource::from));
+                }
+                TilemapField::Tiles => {
+                    tiles = Some(map.next_value_seed(TilesDe {
+                        registry: self.registry,
+                    })?);
+                }
+            }
+        }
+
+        Ok(TilemapData {
+            size: size.ok_or_else(|| A::Error::missing_field("size"))?,
+            tile_size: tile_size.ok_or_else(|| A::Error::missing_field("tile_size"))?,
+            grid_size: grid_size.ok_or_else(|| A::Error::missing_field("grid_size"))?,
+            texture: texture.flatten(),
+            tiles: tiles.unwrap_or_default(),
+        })
+    }
+}
+
+struct TilesDe<'a> {
+    registry: &'a TypeRegistryInternal,
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for TilesDe<'a> {
+    type Value = Vec<TileRecord>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for TilesDe<'a> {
+    type Value = Vec<TileRecord>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a list of tiles")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut tiles = Vec::new();
+
+        while let Some(tile) = seq.next_element_seed(TileDe {
+            registry: self.registry,
+        })? {
+            tiles.push(tile);
+        }
+
+        Ok(tiles)
+    }
+}
+
+struct TileDe<'a> {
+    registry: &'a TypeRegistryInternal,
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for TileDe<'a> {
+    type Value = TileRecord;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_struct("Tile", TILE_FIELDS, self)
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for TileDe<'a> {
+    type Value = TileRecord;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a tile")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut pos = None;
+        let mut texture = None;
+        let mut flip = TileFlip::default();
+        let mut color = TileColor(Color::WHITE);
+        let mut components = Vec::new();
+
+        while let Some(field) = map.next_key()? {
+            match field {
+                TileField::Pos => {
+                    let UVec2Def { x, y } = map.next_value()?;
+                    pos = Some(TilePos { x, y });
+                }
+                TileField::Texture => texture = Some(TileTextureIndex(map.next_value()?)),
+                TileField::Flip => {
+                    let FlipDef { x, y, d } = map.next_value()?;
+                    flip = TileFlip { x, y, d };
+                }
+                TileField::Color => color = TileColor(map.next_value()?),
+                TileField::Components => {
+                    components = map.next_value_seed(ComponentsDe {
+                        registry: self.registry,
+                    })?;
+                }
+            }
+        }
+
+        Ok(TileRecord {
+            pos: pos.ok_or_else(|| A::Error::missing_field("pos"))?,
+            texture: texture.ok_or_else(|| A::Error::missing_field("texture"))?,
+            flip,
+            color,
+            components,
+        })
+    }
+}
+
+struct ComponentsDe<'a> {
+    registry: &'a TypeRegistryInternal,
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for ComponentsDe<'a> {
+    type Value = Vec<Box<dyn Reflect>>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for ComponentsDe<'a> {
+    type Value = Vec<Box<dyn Reflect>>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a list of reflected components")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut components = Vec::new();
+
+        while let Some(component) =
+            seq.next_element_seed(UntypedReflectDeserializer::new(self.registry))?
+        {
+            components.push(component);
+        }
+
+        Ok(components)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Component, Reflect, Default)]
+    #[reflect(Component)]
+    struct Health {
+        value: u32,
+    }
+
+    #[test]
+    fn save_load_round_trip() {
+        let mut registry = TypeRegistryInternal::default();
+        registry.register::<Health>();
+
+        let data = TilemapData {
+            size: TilemapSize { x: 4, y: 3 },
+            tile_size: TilemapTileSize { x: 16.0, y: 16.0 },
+            grid_size: TilemapGridSize { x: 16.0, y: 16.0 },
+            texture: Some(TextureSource::Single("tiles.png".into())),
+            tiles: vec![TileRecord {
+                pos: TilePos { x: 2, y: 1 },
+                texture: TileTextureIndex(7),
+                flip: TileFlip {
+                    x: true,
+                    y: false,
+                    d: true,
+                },
+                color: TileColor(Color::RED),
+                components: vec![Box::new(Health { value: 42 })],
+            }],
+        };
+
+        let mut bytes = Vec::new();
+        RonFormat.write(&data, &registry, &mut bytes).unwrap();
+        let loaded = RonFormat.read(&registry, &mut bytes.as_slice()).unwrap();
+
+        assert_eq!((loaded.size.x, loaded.size.y), (4, 3));
+        assert_eq!(loaded.tile_size.x, 16.0);
+        assert_eq!(loaded.grid_size.y, 16.0);
+        assert_eq!(loaded.texture, data.texture);
+        assert_eq!(loaded.tiles.len(), 1);
+
+        let tile = &loaded.tiles[0];
+        assert_eq!((tile.pos.x, tile.pos.y), (2, 1));
+        assert_eq!(tile.texture.0, 7);
+        assert_eq!((tile.flip.x, tile.flip.y, tile.flip.d), (true, false, true));
+        assert_eq!(tile.color.0, Color::RED);
+        assert_eq!(tile.components.len(), 1);
+        assert_eq!(
+            tile.components[0].reflect_partial_eq(data.tiles[0].components[0].as_ref()),
+            Some(true)
+        );
+    }
+}
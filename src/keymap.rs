@@ -0,0 +1,493 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::path::Path;
+use std::str::FromStr;
+
+use bevy::prelude::*;
+use bevy_editor_pls::egui::{self, Key};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::EditorError;
+
+// egui can't tell the name of a key back, so the keys get looked up here
+const KEYS: [Key; 73] = [
+    Key::ArrowDown,
+    Key::ArrowLeft,
+    Key::ArrowRight,
+    Key::ArrowUp,
+    Key::Escape,
+    Key::Tab,
+    Key::Backspace,
+    Key::Enter,
+    Key::Space,
+    Key::Insert,
+    Key::Delete,
+    Key::Home,
+    Key::End,
+    Key::PageUp,
+    Key::PageDown,
+    Key::Minus,
+    Key::PlusEquals,
+    Key::Num0,
+    Key::Num1,
+    Key::Num2,
+    Key::Num3,
+    Key::Num4,
+    Key::Num5,
+    Key::Num6,
+    Key::Num7,
+    Key::Num8,
+    Key::Num9,
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+    Key::F1,
+    Key::F2,
+    Key::F3,
+    Key::F4,
+    Key::F5,
+    Key::F6,
+    Key::F7,
+    Key::F8,
+    Key::F9,
+    Key::F10,
+    Key::F11,
+    Key::F12,
+    Key::F13,
+    Key::F14,
+    Key::F15,
+    Key::F16,
+    Key::F17,
+    Key::F18,
+    Key::F19,
+    Key::F20,
+];
+
+/// Everything in the editor, that can be done with a keyboard shortcut
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum EditorAction {
+    FlipX,
+    FlipY,
+    FlipDiagonal,
+    RotateClockwise,
+    RotateCounterclockwise,
+    Undo,
+    Redo,
+    Copy,
+    Cut,
+    Paste,
+    /// Drops whatever the current tool is holding
+    Cancel,
+    NextTool,
+    PreviousTool,
+    /// Selects the tool with that index, counting from zero
+    SelectTool(u8),
+    PaletteLeft,
+    PaletteRight,
+    PaletteUp,
+    PaletteDown,
+    /// Picks the next of the overlapping tilemaps under the cursor
+    CycleTilemaps,
+}
+
+impl EditorAction {
+    pub const ALL: [EditorAction; 27] = [
+        Self::FlipX,
+        Self::FlipY,
+        Self::FlipDiagonal,
+        Self::RotateClockwise,
+        Self::RotateCounterclockwise,
+        Self::Undo,
+        Self::Redo,
+        Self::Copy,
+        Self::Cut,
+        Self::Paste,
+        Self::Cancel,
+        Self::NextTool,
+        Self::PreviousTool,
+        Self::SelectTool(0),
+        Self::SelectTool(1),
+        Self::SelectTool(2),
+        Self::SelectTool(3),
+        Self::SelectTool(4),
+        Self::SelectTool(5),
+        Self::SelectTool(6),
+        Self::SelectTool(7),
+        Self::SelectTool(8),
+        Self::PaletteLeft,
+        Self::PaletteRight,
+        Self::PaletteUp,
+        Self::PaletteDown,
+        Self::CycleTilemaps,
+    ];
+}
+
+impl fmt::Display for EditorAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::FlipX => "Horizontal flip",
+            Self::FlipY => "Vertical flip",
+            Self::FlipDiagonal => "Diagonal flip",
+            Self::RotateClockwise => "Rotate clockwise",
+            Self::RotateCounterclockwise => "Rotate counterclockwise",
+            Self::Undo => "Undo",
+            Self::Redo => "Redo",
+            Self::Copy => "Copy",
+            Self::Cut => "Cut",
+            Self::Paste => "Paste",
+            Self::Cancel => "Cancel",
+            Self::NextTool => "Next tool",
+            Self::PreviousTool => "Previous tool",
+            Self::SelectTool(idx) => return write!(f, "Tool #{}", idx + 1),
+            Self::PaletteLeft => "Palette left",
+            Self::PaletteRight => "Palette right",
+            Self::PaletteUp => "Palette up",
+            Self::PaletteDown => "Palette down",
+            Self::CycleTilemaps => "Cycle tilemaps",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+/// Everything in the editor, that lasts as long as a modifier key is held down
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum EditorHoldAction {
+    /// Turns any tool into the tile picker
+    PickTile,
+    /// Swaps between filling rectangles and drawing their outlines
+    SwapRectOutline,
+}
+
+impl EditorHoldAction {
+    pub const ALL: [EditorHoldAction; 2] = [Self::PickTile, Self::SwapRectOutline];
+}
+
+impl fmt::Display for EditorHoldAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::PickTile => "Pick tiles",
+            Self::SwapRectOutline => "Rectangle outline",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Modifier {
+    /// `Ctrl`, or `Cmd` on Mac
+    Command,
+    Shift,
+    Alt,
+}
+
+impl Modifier {
+    pub const ALL: [Modifier; 3] = [Self::Command, Self::Shift, Self::Alt];
+
+    fn is_down(self, modifiers: egui::Modifiers) -> bool {
+        match self {
+            Self::Command => modifiers.command,
+            Self::Shift => modifiers.shift,
+            Self::Alt => modifiers.alt,
+        }
+    }
+}
+
+impl fmt::Display for Modifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Command => "Ctrl",
+            Self::Shift => "Shift",
+            Self::Alt => "Alt",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+/// A key along with the modifiers, that have to be held down.
+/// `command` is `Ctrl`, or `Cmd` on Mac. The modifiers have to match
+/// exactly, so `R` and `Shift+R` can do different things.
+///
+/// Written as text, like `Ctrl+Shift+Z`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub key: Key,
+    pub command: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+impl KeyChord {
+    pub const fn new(key: Key) -> Self {
+        Self {
+            key,
+            command: false,
+            shift: false,
+            alt: false,
+        }
+    }
+
+    pub const fn command(mut self) -> Self {
+        self.command = true;
+        self
+    }
+
+    pub const fn shift(mut self) -> Self {
+        self.shift = true;
+        self
+    }
+
+    pub const fn alt(mut self) -> Self {
+        self.alt = true;
+        self
+    }
+
+    fn matches(&self, key: Key, modifiers: egui::Modifiers) -> bool {
+        self.key == key
+            && self.command == modifiers.command
+            && self.shift == modifiers.shift
+            && self.alt == modifiers.alt
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.command {
+            write!(f, "Ctrl+")?;
+        }
+        if self.shift {
+            write!(f, "Shift+")?;
+        }
+        if self.alt {
+            write!(f, "Alt+")?;
+        }
+
+        write!(f, "{}", self.key.name())
+    }
+}
+
+impl FromStr for KeyChord {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (modifiers, key) = s.rsplit_once('+').unwrap_or(("", s));
+        let key = KEYS
+            .into_iter()
+            .find(|x| x.name().eq_ignore_ascii_case(key.trim()))
+            .ok_or_else(|| format!("Unknown key {key:?}"))?;
+
+        modifiers
+            .split('+')
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .try_fold(Self::new(key), |chord, modifier| {
+                match modifier.to_ascii_lowercase().as_str() {
+                    "ctrl" | "cmd" => Ok(chord.command()),
+                    "shift" => Ok(chord.shift()),
+                    "alt" => Ok(chord.alt()),
+                    _ => Err(format!("Unknown modifier {modifier:?}")),
+                }
+            })
+    }
+}
+
+impl Serialize for KeyChord {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyChord {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// The keyboard shortcuts of the editor. Insert it before adding
+/// [`crate::TilemapEditorPlugin`] to override the defaults, or change
+/// it right in the editor window. Actions can be left unbound.
+#[derive(Clone, Debug, Resource, Serialize, Deserialize)]
+pub struct TilemapEditorKeymap {
+    bindings: HashMap<EditorAction, KeyChord>,
+    #[serde(default = "default_holds")]
+    holds: HashMap<EditorHoldAction, Modifier>,
+}
+
+fn default_holds() -> HashMap<EditorHoldAction, Modifier> {
+    HashMap::from([
+        (EditorHoldAction::PickTile, Modifier::Alt),
+        (EditorHoldAction::SwapRectOutline, Modifier::Shift),
+    ])
+}
+
+impl Default for TilemapEditorKeymap {
+    fn default() -> Self {
+        Self {
+            holds: default_holds(),
+            bindings: HashMap::from([
+                (EditorAction::FlipX, KeyChord::new(Key::H)),
+                (EditorAction::FlipY, KeyChord::new(Key::V)),
+                (EditorAction::FlipDiagonal, KeyChord::new(Key::D)),
+                (EditorAction::RotateClockwise, KeyChord::new(Key::R)),
+                (
+                    EditorAction::RotateCounterclockwise,
+                    KeyChord::new(Key::R).shift(),
+                ),
+                (EditorAction::Undo, KeyChord::new(Key::Z).command()),
+                (EditorAction::Redo, KeyChord::new(Key::Z).command().shift()),
+                (EditorAction::Copy, KeyChord::new(Key::C).command()),
+                (EditorAction::Cut, KeyChord::new(Key::X).command()),
+                (EditorAction::Paste, KeyChord::new(Key::V).command()),
+                (EditorAction::Cancel, KeyChord::new(Key::Escape)),
+                (EditorAction::SelectTool(0), KeyChord::new(Key::Num1)),
+                (EditorAction::SelectTool(1), KeyChord::new(Key::Num2)),
+                (EditorAction::SelectTool(2), KeyChord::new(Key::Num3)),
+                (EditorAction::SelectTool(3), KeyChord::new(Key::Num4)),
+                (EditorAction::SelectTool(4), KeyChord::new(Key::Num5)),
+                (EditorAction::SelectTool(5), KeyChord::new(Key::Num6)),
+                (EditorAction::SelectTool(6), KeyChord::new(Key::Num7)),
+                (EditorAction::SelectTool(7), KeyChord::new(Key::Num8)),
+                (EditorAction::SelectTool(8), KeyChord::new(Key::Num9)),
+                (EditorAction::PaletteLeft, KeyChord::new(Key::ArrowLeft)),
+                (EditorAction::PaletteRight, KeyChord::new(Key::ArrowRight)),
+                (EditorAction::PaletteUp, KeyChord::new(Key::ArrowUp)),
+                (EditorAction::PaletteDown, KeyChord::new(Key::ArrowDown)),
+                (EditorAction::CycleTilemaps, KeyChord::new(Key::Tab)),
+            ]),
+        }
+    }
+}
+
+impl TilemapEditorKeymap {
+    /// A keymap with nothing bound at all
+    pub fn unbound() -> Self {
+        Self {
+            bindings: HashMap::new(),
+            holds: HashMap::new(),
+        }
+    }
+
+    pub fn chord(&self, action: EditorAction) -> Option<KeyChord> {
+        self.bindings.get(&action).copied()
+    }
+
+    /// Binds the action to the chord. `None` unbinds it.
+    pub fn bind(&mut self, action: EditorAction, chord: Option<KeyChord>) {
+        match chord {
+            Some(chord) => self.bindings.insert(action, chord),
+            None => self.bindings.remove(&action),
+        };
+    }
+
+    /// Whether the shortcut of the action has been pressed this frame.
+    /// Nothing counts as pressed while the user is typing into some widget.
+    pub fn pressed(&self, action: EditorAction, ctx: &egui::Context) -> bool {
+        let Some(chord) = self.chord(action) else { return false; };
+
+        !ctx.wants_keyboard_input()
+            && ctx.input(|x| {
+                x.events.iter().any(|event| {
+                    matches!(
+                        event,
+                        egui::Event::Key { key, pressed: true, modifiers, .. }
+                            if chord.matches(*key, *modifiers)
+                    )
+                })
+            })
+    }
+
+    pub fn modifier(&self, action: EditorHoldAction) -> Option<Modifier> {
+        self.holds.get(&action).copied()
+    }
+
+    /// Binds the action to the modifier. `None` unbinds it.
+    pub fn bind_modifier(&mut self, action: EditorHoldAction, modifier: Option<Modifier>) {
+        match modifier {
+            Some(modifier) => self.holds.insert(action, modifier),
+            None => self.holds.remove(&action),
+        };
+    }
+
+    /// Whether the modifier of the action is being held down
+    pub fn held(&self, action: EditorHoldAction, ctx: &egui::Context) -> bool {
+        let Some(modifier) = self.modifier(action) else { return false; };
+
+        ctx.input(|x| modifier.is_down(x.modifiers))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, EditorError> {
+        Ok(ron::de::from_reader(File::open(path)?)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), EditorError> {
+        ron::ser::to_writer_pretty(File::create(path)?, self, ron::ser::PrettyConfig::default())?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chord_round_trip() {
+        let chord: KeyChord = "Ctrl+Shift+Plus".parse().unwrap();
+
+        assert_eq!(chord, KeyChord::new(Key::PlusEquals).command().shift());
+        assert_eq!(chord.to_string(), "Ctrl+Shift+Plus");
+        assert_eq!(chord.to_string().parse(), Ok(chord));
+    }
+
+    #[test]
+    fn chord_parse_errors() {
+        assert!("Ctrl+".parse::<KeyChord>().is_err());
+        assert!("Hyper+Z".parse::<KeyChord>().is_err());
+        assert!("Ctrl+Nope".parse::<KeyChord>().is_err());
+    }
+
+    #[test]
+    fn keymap_ron_round_trip() {
+        let mut keymap = TilemapEditorKeymap::default();
+        keymap.bind(EditorAction::Undo, Some(KeyChord::new(Key::U).alt()));
+        keymap.bind_modifier(EditorHoldAction::PickTile, None);
+
+        let text = ron::to_string(&keymap).unwrap();
+        let loaded: TilemapEditorKeymap = ron::from_str(&text).unwrap();
+
+        for action in EditorAction::ALL {
+            assert_eq!(loaded.chord(action), keymap.chord(action));
+        }
+        for action in EditorHoldAction::ALL {
+            assert_eq!(loaded.modifier(action), keymap.modifier(action));
+        }
+    }
+}
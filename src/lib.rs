@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use bevy::prelude::*;
 use bevy_editor_pls::{
     editor_window::{EditorWindow, EditorWindowContext},
@@ -6,12 +8,16 @@ use bevy_editor_pls::{
 
 mod coord_utils;
 mod error;
+mod format;
+mod keymap;
 mod queries;
 mod state;
 mod tile_data;
 
 pub use error::EditorError;
-pub use state::EditorState;
+pub use format::{RonFormat, TextureSource, TileRecord, TilemapData, TilemapFormat};
+pub use keymap::{EditorAction, EditorHoldAction, KeyChord, Modifier, TilemapEditorKeymap};
+pub use state::{EditorState, TileProperties, Tool, ToolContext};
 pub use tile_data::EditorTileDataRegistry;
 
 pub struct TilemapEditorWindow;
@@ -33,12 +39,59 @@ impl EditorWindow for TilemapEditorWindow {
     }
 }
 
-#[derive(Debug, Clone, Copy, Default)]
+/// The space between the edges of the atlas image and the tiles on it.
+/// Insert it into the tilemap entity, if its atlas has any.
+#[derive(Component, Reflect, Default, Clone, Copy, Debug)]
+#[reflect(Component)]
+pub struct TilemapAtlasMargin {
+    pub x: f32,
+    pub y: f32,
+}
+
 pub struct TilemapEditorPlugin;
 
 impl Plugin for TilemapEditorPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<EditorTileDataRegistry>()
+        app.register_type::<TilemapAtlasMargin>()
+            .init_resource::<EditorTileDataRegistry>()
+            .init_resource::<TilemapEditorKeymap>()
+            .init_resource::<format::TilemapFormats>()
+            .init_resource::<state::EditorTools>()
             .add_editor_window::<TilemapEditorWindow>();
     }
 }
+
+/// Makes a format available in the editor's save and load menus.
+/// Has to be called after adding [`TilemapEditorPlugin`].
+pub trait AddTilemapEditorFormat {
+    fn add_tilemap_editor_format(&mut self, format: impl TilemapFormat) -> &mut Self;
+}
+
+impl AddTilemapEditorFormat for App {
+    fn add_tilemap_editor_format(&mut self, format: impl TilemapFormat) -> &mut Self {
+        self.world
+            .get_resource_mut::<format::TilemapFormats>()
+            .expect("TilemapEditorPlugin has to be added before the formats")
+            .0
+            .push(Arc::new(format));
+
+        self
+    }
+}
+
+/// Adds a tool to the end of the editor's tool row from anywhere the `App`
+/// is available. Has to be called after adding [`TilemapEditorPlugin`].
+pub trait AddTilemapEditorTool {
+    fn add_tilemap_editor_tool<T: Tool + Default + 'static>(&mut self) -> &mut Self;
+}
+
+impl AddTilemapEditorTool for App {
+    fn add_tilemap_editor_tool<T: Tool + Default + 'static>(&mut self) -> &mut Self {
+        self.world
+            .get_resource_mut::<state::EditorTools>()
+            .expect("TilemapEditorPlugin has to be added before the tools")
+            .add::<T>();
+
+        self
+    }
+}
@@ -2,6 +2,8 @@ use bevy::{ecs::query::WorldQuery, prelude::*};
 use bevy_ecs_tilemap::prelude::*;
 use bevy_editor_pls::egui;
 
+use crate::coord_utils::convex_hull;
+
 #[derive(bevy::ecs::query::WorldQuery)]
 #[world_query(mutable)]
 pub struct TilemapQuery {
@@ -14,37 +16,221 @@ pub struct TilemapQuery {
     pub transform: &'static GlobalTransform,
     pub storage: &'static mut TileStorage,
     pub ty: &'static TilemapType,
+    pub spacing: Option<&'static TilemapSpacing>,
+    pub margin: Option<&'static crate::TilemapAtlasMargin>,
+    pub render_settings: Option<&'static TilemapRenderSettings>,
 }
 
+/// Maps the points of the tilemap's local space onto the
+/// viewport and back, taking all the transforms into account.
 #[derive(Debug)]
 pub struct TilemapPoints {
     viewport_rect: egui::Rect,
-    map_lower_left: Vec2,
-    map_top_right: Vec2,
-    grid_sample_top_right: Vec2,
+    camera_viewport_size: Vec2,
+    local_to_ndc: Mat4,
+    map_size: TilemapSize,
+    grid_size: TilemapGridSize,
+    tile_size: TilemapTileSize,
+    map_type: TilemapType,
+    chunk_size: UVec2,
 }
 
 impl TilemapPoints {
-    pub fn tilemap_rect(&self) -> egui::Rect {
-        let p1 = self.bevy_viewport_to_egui(self.map_lower_left);
-        let p2 = self.bevy_viewport_to_egui(self.map_top_right);
+    pub fn local_to_egui(&self, pos: Vec2) -> egui::Pos2 {
+        let ndc = self.local_to_ndc.project_point3(pos.extend(0.0)).truncate();
+        let v = (ndc + Vec2::ONE) / 2.0 * self.camera_viewport_size;
+
+        self.bevy_viewport_to_egui(v) + self.viewport_rect.min.to_vec2()
+    }
 
-        egui::Rect {
-            min: egui::pos2(p1.x, p2.y),
-            max: egui::pos2(p2.x, p1.y),
+    /// Finds the point of the tilemap's plane under `pos`
+    pub fn egui_to_local(&self, pos: egui::Pos2) -> Option<Vec2> {
+        let pos = pos - self.viewport_rect.min.to_vec2();
+        let v = Vec2::new(pos.x, self.viewport_rect.size().y - pos.y);
+        let ndc = v * 2.0 / self.camera_viewport_size - Vec2::ONE;
+        let ndc_to_local = self.local_to_ndc.inverse();
+        let near = ndc_to_local.project_point3(ndc.extend(1.0));
+        let far = ndc_to_local.project_point3(ndc.extend(f32::EPSILON));
+
+        // The tilemap is seen from the side
+        if (near.z - far.z).abs() <= f32::EPSILON {
+            return None;
         }
-        .translate(self.viewport_rect.min.to_vec2())
+
+        let t = near.z / (near.z - far.z);
+
+        Some((near + (far - near) * t).truncate())
     }
 
-    pub fn grid_sample_rect(&self) -> egui::Rect {
-        let p1 = self.bevy_viewport_to_egui(self.map_lower_left);
-        let p2 = self.bevy_viewport_to_egui(self.grid_sample_top_right);
+    pub fn hovered_tile(&self, pos: egui::Pos2) -> Option<TilePos> {
+        let local = self.egui_to_local(pos)?;
+
+        TilePos::from_world_pos(&local, &self.map_size, &self.grid_size, &self.map_type)
+    }
+
+    fn tile_outline_local(&self, pos: TilePos) -> Vec<Vec2> {
+        let center = pos.center_in_world(&self.grid_size, &self.map_type);
+        let half = Vec2::from(self.grid_size) / 2.0;
+        let corners: &[Vec2] = match self.map_type {
+            TilemapType::Square => &[
+                Vec2::new(-1.0, 1.0),
+                Vec2::new(1.0, 1.0),
+                Vec2::new(1.0, -1.0),
+                Vec2::new(-1.0, -1.0),
+            ],
+            // Both diamond and staggered maps are made of diamonds
+            TilemapType::Isometric(_) => &[
+                Vec2::new(0.0, 1.0),
+                Vec2::new(1.0, 0.0),
+                Vec2::new(0.0, -1.0),
+                Vec2::new(-1.0, 0.0),
+            ],
+            // Pointy-topped hexagons
+            TilemapType::Hexagon(
+                HexCoordSystem::Row | HexCoordSystem::RowEven | HexCoordSystem::RowOdd,
+            ) => &[
+                Vec2::new(0.0, 1.0),
+                Vec2::new(1.0, 0.5),
+                Vec2::new(1.0, -0.5),
+                Vec2::new(0.0, -1.0),
+                Vec2::new(-1.0, -0.5),
+                Vec2::new(-1.0, 0.5),
+            ],
+            // Flat-topped hexagons
+            TilemapType::Hexagon(_) => &[
+                Vec2::new(-0.5, 1.0),
+                Vec2::new(0.5, 1.0),
+                Vec2::new(1.0, 0.0),
+                Vec2::new(0.5, -1.0),
+                Vec2::new(-0.5, -1.0),
+                Vec2::new(-1.0, 0.0),
+            ],
+        };
+
+        corners.iter().map(|x| center + *x * half).collect()
+    }
+
+    /// The outline of the grid cell of the tile
+    pub fn tile_outline(&self, pos: TilePos) -> Vec<egui::Pos2> {
+        self.tile_outline_local(pos)
+            .into_iter()
+            .map(|x| self.local_to_egui(x))
+            .collect()
+    }
+
+    /// The corners of the quad the tile's texture gets drawn on, in the order:
+    /// top-left, top-right, bottom-right, bottom-left.
+    pub fn tile_quad(&self, pos: TilePos) -> [egui::Pos2; 4] {
+        let center = pos.center_in_world(&self.grid_size, &self.map_type);
+        let half = Vec2::from(self.tile_size) / 2.0;
+
+        [
+            center + Vec2::new(-half.x, half.y),
+            center + half,
+            center + Vec2::new(half.x, -half.y),
+            center - half,
+        ]
+        .map(|x| self.local_to_egui(x))
+    }
+
+    /// The outline of the rectangle of tiles spanned by two corners
+    pub fn region_outline(&self, a: TilePos, b: TilePos) -> Vec<egui::Pos2> {
+        let corners = [a, b, TilePos { x: a.x, y: b.y }, TilePos { x: b.x, y: a.y }];
+        let points = corners
+            .into_iter()
+            .flat_map(|pos| self.tile_outline_local(pos))
+            .collect();
+
+        convex_hull(points)
+            .into_iter()
+            .map(|x| self.local_to_egui(x))
+            .collect()
+    }
+
+    pub fn tilemap_outline(&self) -> Vec<egui::Pos2> {
+        self.region_outline(
+            TilePos { x: 0, y: 0 },
+            TilePos {
+                x: self.map_size.x.saturating_sub(1),
+                y: self.map_size.y.saturating_sub(1),
+            },
+        )
+    }
 
-        egui::Rect {
-            min: egui::pos2(p1.x, p2.y),
-            max: egui::pos2(p2.x, p1.y),
+    /// The size of the chunks, that the tilemap gets rendered in
+    pub fn chunk_size(&self) -> UVec2 {
+        self.chunk_size
+    }
+
+    /// Whether the cell borders form straight lines across the whole map
+    pub fn has_straight_grid(&self) -> bool {
+        matches!(
+            self.map_type,
+            TilemapType::Square | TilemapType::Isometric(IsoCoordSystem::Diamond)
+        )
+    }
+
+    // The center of the first tile and the steps to its neighbours along both axes.
+    // Hexagonal and staggered maps zig-zag around this, but never by more than a tile.
+    fn tile_basis(&self) -> (Vec2, Vec2, Vec2) {
+        let center = |x, y| TilePos { x, y }.center_in_world(&self.grid_size, &self.map_type);
+        let origin = center(0, 0);
+
+        (origin, center(1, 0) - origin, center(0, 1) - origin)
+    }
+
+    /// Maps a point on the grid onto the viewport. Integer coordinates are the
+    /// corners between the cells. Only makes sense for straight grids.
+    pub fn grid_point(&self, pos: Vec2) -> egui::Pos2 {
+        let (origin, step_x, step_y) = self.tile_basis();
+        let pos = pos - Vec2::splat(0.5);
+
+        self.local_to_egui(origin + pos.x * step_x + pos.y * step_y)
+    }
+
+    /// The tiles, that fall into the viewport, as a rectangle of tile positions.
+    /// The rectangle is generous, so parts of the tiles at its edges may
+    /// be out of sight. `None` means the tilemap isn't visible at all.
+    pub fn grid_sample_rect(&self) -> Option<(TilePos, TilePos)> {
+        const MARGIN: f32 = 1.0;
+
+        let (origin, step_x, step_y) = self.tile_basis();
+        let local_to_grid = Mat2::from_cols(step_x, step_y).inverse();
+        let rect = self.viewport_rect;
+        let corners = [
+            rect.left_top(),
+            rect.right_top(),
+            rect.right_bottom(),
+            rect.left_bottom(),
+        ]
+        .map(|x| self.egui_to_local(x).map(|x| local_to_grid * (x - origin)));
+        let map_max = Vec2::new(self.map_size.x as f32, self.map_size.y as f32) - Vec2::ONE;
+
+        // When the plane is seen from the side, the whole map has to be checked
+        let (min, max) = corners
+            .into_iter()
+            .try_fold(
+                (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+                |(min, max), x| x.map(|x| (min.min(x), max.max(x))),
+            )
+            .unwrap_or((Vec2::ZERO, map_max));
+        let min = (min - MARGIN).round().max(Vec2::ZERO);
+        let max = (max + MARGIN).round().min(map_max);
+
+        if min.x > max.x || min.y > max.y {
+            return None;
         }
-        .translate(self.viewport_rect.min.to_vec2())
+
+        Some((
+            TilePos {
+                x: min.x as u32,
+                y: min.y as u32,
+            },
+            TilePos {
+                x: max.x as u32,
+                y: max.y as u32,
+            },
+        ))
     }
 
     fn bevy_viewport_to_egui(&self, v: Vec2) -> egui::Pos2 {
@@ -55,7 +241,7 @@ impl TilemapPoints {
 #[derive(bevy::ecs::query::WorldQuery)]
 pub struct TilemapCameraQuery {
     camera: &'static Camera,
-    transform: &'static Transform,
+    transform: &'static GlobalTransform,
 }
 
 impl<'a> TilemapCameraQueryItem<'a> {
@@ -63,36 +249,29 @@ impl<'a> TilemapCameraQueryItem<'a> {
         self.camera.is_active
     }
 
-    // FIXME this 100% doesn't work if the tilemap itself is also transformed.
     pub fn tilemap_points(
         &self,
         viewport_rect: egui::Rect,
         tilemap: &TilemapQueryReadOnlyItem,
-    ) -> TilemapPoints {
-        let reftile_origin_off = Vec2::from(tilemap.tile_size) / 2.0f32;
-        let map_lower_left = tilemap.transform.translation().truncate() - reftile_origin_off;
-        let grid_sample_top_right = map_lower_left + Vec2::from(tilemap.grid_size);
-        let map_top_right = map_lower_left
-            + Vec2::new(
-                (tilemap.size.x as f32) * tilemap.grid_size.x,
-                (tilemap.size.y as f32) * tilemap.grid_size.y,
-            );
-
-        TilemapPoints {
-            viewport_rect,
-            map_lower_left: self.world_to_viewport(map_lower_left),
-            map_top_right: self.world_to_viewport(map_top_right),
-            grid_sample_top_right: self.world_to_viewport(grid_sample_top_right),
-        }
-    }
+    ) -> Option<TilemapPoints> {
+        let local_to_ndc = self.camera.projection_matrix()
+            * self.transform.compute_matrix().inverse()
+            * tilemap.transform.compute_matrix();
 
-    fn world_to_viewport(&self, pos: Vec2) -> Vec2 {
-        self.camera
-            .world_to_viewport(
-                &(GlobalTransform::IDENTITY * *self.transform),
-                pos.extend(1.0),
-            )
-            .expect("Transforming failed")
+        Some(TilemapPoints {
+            viewport_rect,
+            camera_viewport_size: self.camera.logical_viewport_size()?,
+            local_to_ndc,
+            map_size: *tilemap.size,
+            grid_size: *tilemap.grid_size,
+            tile_size: *tilemap.tile_size,
+            map_type: *tilemap.ty,
+            chunk_size: tilemap
+                .render_settings
+                .copied()
+                .unwrap_or_default()
+                .render_chunk_size,
+        })
     }
 }
 
@@ -0,0 +1,317 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use bevy_editor_pls::egui::{self, Painter};
+
+use super::flip_rotation;
+use super::history::TileSnapshot;
+use super::tools::{rect_tiles, ToolContext};
+use crate::{EditorAction, EditorError};
+
+/// A rectangular block of tiles, that can be carried around and placed
+/// somewhere else. The tiles are stored row by row, starting from the
+/// bottom-left corner. Empty cells are `None`.
+#[derive(Clone)]
+pub struct TileStamp {
+    size: UVec2,
+    tiles: Vec<Option<TileSnapshot>>,
+}
+
+impl TileStamp {
+    pub fn new(size: UVec2, tiles: Vec<Option<TileSnapshot>>) -> Self {
+        assert_eq!((size.x * size.y) as usize, tiles.len());
+
+        Self { size, tiles }
+    }
+
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    /// The non-empty cells of the stamp along with their offsets
+    /// from the bottom-left corner.
+    pub fn tiles(&self) -> impl Iterator<Item = (UVec2, &TileSnapshot)> {
+        let width = self.size.x;
+
+        self.tiles
+            .iter()
+            .enumerate()
+            .filter_map(move |(idx, tile)| {
+                let idx = idx as u32;
+
+                tile.as_ref()
+                    .map(|tile| (UVec2::new(idx % width, idx / width), tile))
+            })
+    }
+
+    // Moves every cell to a new place and transforms its flip accordingly
+    fn remap(
+        &mut self,
+        new_size: UVec2,
+        move_cell: impl Fn(UVec2) -> UVec2,
+        transform_flip: impl Fn(&mut TileFlip),
+    ) {
+        let width = self.size.x;
+        let mut tiles = vec![None; self.tiles.len()];
+
+        for (idx, tile) in std::mem::take(&mut self.tiles).into_iter().enumerate() {
+            let idx = idx as u32;
+            let new_pos = move_cell(UVec2::new(idx % width, idx / width));
+
+            tiles[(new_pos.y * new_size.x + new_pos.x) as usize] = tile.map(|mut tile| {
+                transform_flip(&mut tile.props.flip);
+                tile
+            });
+        }
+
+        self.size = new_size;
+        self.tiles = tiles;
+    }
+
+    /// Rotates the stamp counterclockwise
+    pub fn rotate_plus_90(&mut self) {
+        let size = self.size;
+
+        self.remap(
+            UVec2::new(size.y, size.x),
+            |pos| UVec2::new(size.y - 1 - pos.y, pos.x),
+            flip_rotation::rotate_plus_90,
+        )
+    }
+
+    /// Rotates the stamp clockwise
+    pub fn rotate_minus_90(&mut self) {
+        let size = self.size;
+
+        self.remap(
+            UVec2::new(size.y, size.x),
+            |pos| UVec2::new(pos.y, size.x - 1 - pos.x),
+            flip_rotation::rotate_minus_90,
+        )
+    }
+
+    pub fn flip_x(&mut self) {
+        let size = self.size;
+
+        self.remap(
+            size,
+            |pos| UVec2::new(size.x - 1 - pos.x, pos.y),
+            |flip| flip.x = !flip.x,
+        )
+    }
+
+    pub fn flip_y(&mut self) {
+        let size = self.size;
+
+        self.remap(
+            size,
+            |pos| UVec2::new(pos.x, size.y - 1 - pos.y),
+            |flip| flip.y = !flip.y,
+        )
+    }
+}
+
+impl std::fmt::Debug for TileStamp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TileStamp")
+            .field("size", &self.size)
+            .finish_non_exhaustive()
+    }
+}
+
+// The selection might reach past the tilemap, if it has shrunk since
+fn clamped_selection(ctx: &ToolContext) -> Result<Option<(TilePos, TilePos)>, EditorError> {
+    let Some((a, b)) = ctx.selection() else { return Ok(None); };
+    let size = ctx.tilemap_size()?;
+
+    if size.x == 0 || size.y == 0 {
+        return Ok(None);
+    }
+    let clamp = |pos: TilePos| TilePos {
+        x: pos.x.min(size.x - 1),
+        y: pos.y.min(size.y - 1),
+    };
+
+    Ok(Some((clamp(a), clamp(b))))
+}
+
+/// Copying, cutting and pasting, along with turning the held stamp around.
+/// These work whatever the tool is and wherever the pointer is. Cutting
+/// a locked layer only copies it.
+pub fn clipboard_shortcuts(
+    ctx: &mut ToolContext,
+    stamp: &mut Option<TileStamp>,
+    locked: bool,
+    ui: &egui::Ui,
+) -> Result<(), EditorError> {
+    let copy = ctx.pressed(EditorAction::Copy, ui);
+    let cut = ctx.pressed(EditorAction::Cut, ui);
+
+    if copy || cut {
+        if let Some((a, b)) = clamped_selection(ctx)? {
+            let region = ctx.copy_region(a, b)?;
+
+            ctx.set_clipboard(region);
+
+            if cut && !locked {
+                for pos in rect_tiles(a, b, false) {
+                    ctx.despawn_tile(pos)?;
+                }
+            }
+        }
+    }
+
+    if ctx.pressed(EditorAction::Paste, ui) {
+        *stamp = ctx.clipboard().cloned();
+        ctx.set_selection(None);
+    }
+    if ctx.pressed(EditorAction::Cancel, ui)
+        || (ui.ui_contains_pointer() && ui.input(|x| x.pointer.secondary_pressed()))
+    {
+        *stamp = None;
+    }
+
+    let Some(stamp) = stamp else { return Ok(()); };
+
+    if ctx.pressed(EditorAction::RotateClockwise, ui) {
+        stamp.rotate_minus_90();
+    }
+    if ctx.pressed(EditorAction::RotateCounterclockwise, ui) {
+        stamp.rotate_plus_90();
+    }
+    if ctx.pressed(EditorAction::FlipX, ui) {
+        stamp.flip_x();
+    }
+    if ctx.pressed(EditorAction::FlipY, ui) {
+        stamp.flip_y();
+    }
+
+    Ok(())
+}
+
+/// Shows the stamp under the cursor and places it on a click.
+/// While there's a stamp, it takes over the pointer from the current tool.
+pub fn stamp_ui(
+    stamp: &TileStamp,
+    ctx: &mut ToolContext,
+    hovered_tile: TilePos,
+    ui: &mut egui::Ui,
+    painter: &Painter,
+) -> Result<(), EditorError> {
+    // The stamp hangs off the cursor by its bottom-left corner
+    let size = ctx.tilemap_size()?;
+    let tiles = stamp
+        .tiles()
+        .map(|(offset, tile)| {
+            let pos = TilePos {
+                x: hovered_tile.x + offset.x,
+                y: hovered_tile.y + offset.y,
+            };
+
+            (pos, tile)
+        })
+        .filter(|(pos, _)| pos.x < size.x && pos.y < size.y);
+
+    if ui.input(|x| x.pointer.primary_pressed()) {
+        for (pos, tile) in tiles {
+            ctx.place_tile(pos, tile)?;
+        }
+    } else {
+        for (pos, tile) in tiles {
+            ctx.paint_tile_with(pos, &tile.props, painter)?;
+        }
+    }
+
+    let far_corner = TilePos {
+        x: hovered_tile.x + stamp.size().x - 1,
+        y: hovered_tile.y + stamp.size().y - 1,
+    };
+    painter.add(egui::Shape::closed_line(
+        ctx.region_outline(hovered_tile, far_corner),
+        egui::Stroke::new(1.0, egui::Color32::RED),
+    ));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tools::TileProperties;
+    use super::*;
+
+    // A 3x2 stamp with an empty cell and a flipped tile
+    fn stamp() -> TileStamp {
+        let tiles = [Some(0), Some(1), None, Some(3), Some(4), Some(5)]
+            .into_iter()
+            .map(|texture| {
+                texture.map(|texture| TileSnapshot {
+                    props: TileProperties {
+                        texture: TileTextureIndex(texture),
+                        flip: TileFlip {
+                            x: texture == 4,
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    components: Vec::new(),
+                })
+            })
+            .collect();
+
+        TileStamp::new(UVec2::new(3, 2), tiles)
+    }
+
+    fn cells(stamp: &TileStamp) -> Vec<(UVec2, u32, (bool, bool, bool))> {
+        stamp
+            .tiles()
+            .map(|(pos, tile)| {
+                let flip = tile.props.flip;
+
+                (pos, tile.props.texture.0, (flip.x, flip.y, flip.d))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn four_rotations_restore_the_stamp() {
+        let original = stamp();
+
+        for rotate in [TileStamp::rotate_plus_90, TileStamp::rotate_minus_90] {
+            let mut stamp = original.clone();
+            for _ in 0..4 {
+                rotate(&mut stamp);
+            }
+
+            assert_eq!(stamp.size(), original.size());
+            assert_eq!(cells(&stamp), cells(&original));
+        }
+    }
+
+    #[test]
+    fn rotations_cancel_out() {
+        let original = stamp();
+        let mut stamp = original.clone();
+
+        stamp.rotate_plus_90();
+        assert_eq!(stamp.size(), UVec2::new(2, 3));
+        stamp.rotate_minus_90();
+
+        assert_eq!(cells(&stamp), cells(&original));
+    }
+
+    #[test]
+    fn flips_restore_the_stamp() {
+        let original = stamp();
+        let mut stamp = original.clone();
+
+        stamp.flip_x();
+        assert_eq!(
+            stamp.tiles().next().map(|(_, x)| x.props.texture.0),
+            Some(1)
+        );
+        stamp.flip_x();
+        stamp.flip_y();
+        stamp.flip_y();
+
+        assert_eq!(cells(&stamp), cells(&original));
+    }
+}
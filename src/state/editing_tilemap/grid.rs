@@ -0,0 +1,120 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use bevy_editor_pls::egui;
+
+use crate::queries::TilemapPoints;
+
+/// The look of the grid, that gets painted over the tilemap. Only the
+/// part of the grid, that is visible in the viewport, gets painted.
+#[derive(Debug)]
+pub struct GridOverlay {
+    pub enabled: bool,
+    color: egui::Color32,
+    thickness: f32,
+    major_interval: u32,
+    show_chunks: bool,
+    chunk_color: egui::Color32,
+}
+
+impl Default for GridOverlay {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            color: egui::Color32::from_white_alpha(64),
+            thickness: 1.0,
+            major_interval: 8,
+            show_chunks: false,
+            chunk_color: egui::Color32::from_rgba_unmultiplied(0, 128, 255, 128),
+        }
+    }
+}
+
+impl GridOverlay {
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.enabled, "Show grid");
+
+        if !self.enabled {
+            return;
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Color");
+            ui.color_edit_button_srgba(&mut self.color);
+        });
+        ui.add(egui::Slider::new(&mut self.thickness, 0.5..=4.0).text("Thickness"));
+        ui.add(
+            egui::DragValue::new(&mut self.major_interval)
+                .clamp_range(0..=256)
+                .prefix("Major line every "),
+        );
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.show_chunks, "Chunk borders");
+            ui.color_edit_button_srgba(&mut self.chunk_color);
+        });
+    }
+
+    fn line_stroke(&self, idx: u32, chunk_size: u32) -> egui::Stroke {
+        if self.show_chunks && idx % chunk_size.max(1) == 0 {
+            egui::Stroke::new(self.thickness * 2.0, self.chunk_color)
+        } else if self.major_interval > 0 && idx % self.major_interval == 0 {
+            egui::Stroke::new(self.thickness * 2.0, self.color)
+        } else {
+            egui::Stroke::new(self.thickness, self.color)
+        }
+    }
+
+    /// Paints the grid. Hexagonal and staggered grids don't
+    /// form straight lines, so there are no major lines on them.
+    pub fn paint(&self, points: &TilemapPoints, painter: &egui::Painter) {
+        if !self.enabled {
+            return;
+        }
+
+        let Some((min, max)) = points.grid_sample_rect() else { return; };
+
+        if !points.has_straight_grid() {
+            let stroke = egui::Stroke::new(self.thickness, self.color);
+
+            for x in min.x..=max.x {
+                for y in min.y..=max.y {
+                    painter.add(egui::Shape::closed_line(
+                        points.tile_outline(TilePos { x, y }),
+                        stroke,
+                    ));
+                }
+            }
+
+            return;
+        }
+
+        let chunk_size = points.chunk_size();
+        let (min, max) = (
+            Vec2::new(min.x as f32, min.y as f32),
+            Vec2::new((max.x + 1) as f32, (max.y + 1) as f32),
+        );
+
+        for x in min.x as u32..=max.x as u32 {
+            let x_f = x as f32;
+
+            painter.line_segment(
+                [
+                    points.grid_point(Vec2::new(x_f, min.y)),
+                    points.grid_point(Vec2::new(x_f, max.y)),
+                ],
+                self.line_stroke(x, chunk_size.x),
+            );
+        }
+
+        for y in min.y as u32..=max.y as u32 {
+            let y_f = y as f32;
+
+            painter.line_segment(
+                [
+                    points.grid_point(Vec2::new(min.x, y_f)),
+                    points.grid_point(Vec2::new(max.x, y_f)),
+                ],
+                self.line_stroke(y, chunk_size.y),
+            );
+        }
+    }
+}
@@ -0,0 +1,404 @@
+use std::collections::HashMap;
+
+use bevy::ecs::world::{EntityMut, EntityRef};
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use super::tools::TileProperties;
+use crate::{tile_data::TileData, EditorError};
+
+// Once the history grows past this, the oldest steps get forgotten
+const MAX_HISTORY_LEN: usize = 256;
+
+/// Everything the editor knows about a single tile: its properties
+/// and the values of the tile data components it had.
+pub struct TileSnapshot {
+    pub props: TileProperties,
+    pub components: Vec<(ReflectComponent, Box<dyn Reflect>)>,
+}
+
+impl TileSnapshot {
+    pub(super) fn capture(tile: EntityRef, tile_data: &HashMap<u32, TileData>) -> Option<Self> {
+        let props = TileProperties {
+            color: *tile.get::<TileColor>()?,
+            flip: *tile.get::<TileFlip>()?,
+            texture: *tile.get::<TileTextureIndex>()?,
+        };
+        let components = tile_data
+            .get(&props.texture.0)
+            .map(|data| data.capture(tile))
+            .unwrap_or_default();
+
+        Some(Self { props, components })
+    }
+
+    pub(super) fn insert_components(&self, entity: &mut EntityMut) {
+        self.components
+            .iter()
+            .for_each(|(refl, component)| refl.insert(entity, component.as_ref()))
+    }
+
+    fn remove_components(&self, entity: &mut EntityMut) {
+        self.components
+            .iter()
+            .for_each(|(refl, _)| refl.remove(entity))
+    }
+}
+
+impl Clone for TileSnapshot {
+    fn clone(&self) -> Self {
+        Self {
+            props: self.props,
+            components: self
+                .components
+                .iter()
+                .map(|(refl, value)| (refl.clone(), value.clone_value()))
+                .collect(),
+        }
+    }
+}
+
+struct TileChange {
+    pos: TilePos,
+    before: Option<TileSnapshot>,
+    after: Option<TileSnapshot>,
+}
+
+struct SizeChange {
+    before: TilemapSize,
+    after: TilemapSize,
+    // How much the tiles get moved by
+    offset: IVec2,
+}
+
+/// A single undoable step. A resize is done after the tile changes,
+/// so their positions are the ones from before the resize.
+struct HistoryEntry {
+    tilemap_entity: Entity,
+    changes: Vec<TileChange>,
+    resize: Option<SizeChange>,
+}
+
+impl HistoryEntry {
+    // Makes sure the whole step can be applied, so that it never stops halfway.
+    // The tiles get restored in a tilemap of `size`, or of the current size.
+    fn check(&self, world: &mut World, size: Option<TilemapSize>) -> Result<(), EditorError> {
+        let storage_size = world
+            .query::<&TileStorage>()
+            .get(world, self.tilemap_entity)
+            .map_err(|query_error| EditorError::BadTilemapEntity {
+                tilemap_entity: self.tilemap_entity,
+                query_error,
+            })?
+            .size;
+        let size = size.unwrap_or(storage_size);
+
+        match self
+            .changes
+            .iter()
+            .all(|change| change.pos.x < size.x && change.pos.y < size.y)
+        {
+            true => Ok(()),
+            false => Err(EditorError::StaleHistoryStep {
+                tilemap_entity: self.tilemap_entity,
+            }),
+        }
+    }
+
+    fn undo(&self, world: &mut World) -> Result<(), EditorError> {
+        self.check(world, self.resize.as_ref().map(|x| x.before))?;
+
+        if let Some(resize) = &self.resize {
+            resize_tilemap(world, self.tilemap_entity, resize.before, -resize.offset)?;
+        }
+
+        self.changes.iter().rev().try_for_each(|change| {
+            restore_tile(
+                world,
+                self.tilemap_entity,
+                change.pos,
+                change.after.as_ref(),
+                change.before.as_ref(),
+            )
+        })
+    }
+
+    fn redo(&self, world: &mut World) -> Result<(), EditorError> {
+        self.check(world, None)?;
+
+        self.changes.iter().try_for_each(|change| {
+            restore_tile(
+                world,
+                self.tilemap_entity,
+                change.pos,
+                change.before.as_ref(),
+                change.after.as_ref(),
+            )
+        })?;
+
+        if let Some(resize) = &self.resize {
+            resize_tilemap(world, self.tilemap_entity, resize.after, resize.offset)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Changes the size of the tilemap, moving all of its tiles by `offset`.
+// The tiles, that end up outside of the tilemap, get despawned.
+// Returns the old size of the tilemap.
+fn resize_tilemap(
+    world: &mut World,
+    tilemap_entity: Entity,
+    size: TilemapSize,
+    offset: IVec2,
+) -> Result<TilemapSize, EditorError> {
+    let old_storage = std::mem::replace(
+        &mut *world
+            .query::<&mut TileStorage>()
+            .get_mut(world, tilemap_entity)
+            .map_err(|query_error| EditorError::BadTilemapEntity {
+                tilemap_entity,
+                query_error,
+            })?,
+        TileStorage::empty(size),
+    );
+    let old_size = old_storage.size;
+    let mut storage = TileStorage::empty(size);
+
+    for (idx, tile_entity) in old_storage.iter().enumerate() {
+        let Some(tile_entity) = *tile_entity else { continue };
+        let Some(mut tile) = world.get_entity_mut(tile_entity) else { continue };
+        let idx = idx as u32;
+        let new_pos = IVec2::new((idx % old_size.x) as i32, (idx / old_size.x) as i32) + offset;
+
+        match TilePos::from_i32_pair(new_pos.x, new_pos.y, &size) {
+            Some(new_pos) => {
+                tile.insert(new_pos);
+                storage.set(&new_pos, tile_entity);
+            }
+            None => tile.despawn_recursive(),
+        }
+    }
+
+    world.entity_mut(tilemap_entity).insert((size, storage));
+
+    Ok(old_size)
+}
+
+// Brings the tile at `pos` from the `current` state into the `target` one.
+fn restore_tile(
+    world: &mut World,
+    tilemap_entity: Entity,
+    pos: TilePos,
+    current: Option<&TileSnapshot>,
+    target: Option<&TileSnapshot>,
+) -> Result<(), EditorError> {
+    let mut storage_query = world.query::<&mut TileStorage>();
+    let bad_tilemap = |query_error| EditorError::BadTilemapEntity {
+        tilemap_entity,
+        query_error,
+    };
+    let tile_entity = storage_query
+        .get(world, tilemap_entity)
+        .map_err(bad_tilemap)?
+        .get(&pos)
+        .filter(|tile_entity| world.get_entity(*tile_entity).is_some());
+
+    match (tile_entity, target) {
+        (None, None) => (),
+        (Some(tile_entity), None) => {
+            storage_query
+                .get_mut(world, tilemap_entity)
+                .map_err(bad_tilemap)?
+                .remove(&pos);
+            world.entity_mut(tile_entity).despawn_recursive();
+        }
+        (None, Some(target)) => {
+            let mut tile_entity_mut = world.spawn(TileBundle {
+                tilemap_id: TilemapId(tilemap_entity),
+                position: pos,
+                texture_index: target.props.texture,
+                flip: target.props.flip,
+                color: target.props.color,
+                ..default()
+            });
+            target.insert_components(&mut tile_entity_mut);
+            let tile_entity = tile_entity_mut.id();
+
+            storage_query
+                .get_mut(world, tilemap_entity)
+                .map_err(bad_tilemap)?
+                .set(&pos, tile_entity);
+        }
+        (Some(tile_entity), Some(target)) => {
+            let mut tile_entity_mut = world.entity_mut(tile_entity);
+
+            if let Some(current) = current {
+                current.remove_components(&mut tile_entity_mut);
+            }
+            tile_entity_mut.insert((target.props.color, target.props.flip, target.props.texture));
+            target.insert_components(&mut tile_entity_mut);
+        }
+    }
+
+    Ok(())
+}
+
+/// The undo/redo history of all tile edits made through the editor.
+/// Changes get accumulated into a pending step until [`EditHistory::commit`]
+/// is called, so that a whole mouse stroke can be undone at once.
+/// Every tilemap gets undone and redone on its own, the steps of the
+/// other tilemaps stay where they are.
+#[derive(Default)]
+pub struct EditHistory {
+    undo_stack: Vec<HistoryEntry>,
+    redo_stack: Vec<HistoryEntry>,
+    pending: Option<HistoryEntry>,
+    revision: u64,
+}
+
+impl EditHistory {
+    pub fn record(
+        &mut self,
+        tilemap_entity: Entity,
+        pos: TilePos,
+        before: Option<TileSnapshot>,
+        after: Option<TileSnapshot>,
+    ) {
+        if !matches!(&self.pending, Some(x) if x.tilemap_entity == tilemap_entity) {
+            self.commit();
+        }
+
+        self.forget_redo(tilemap_entity);
+        self.revision += 1;
+        let pending = self.pending.get_or_insert_with(|| HistoryEntry {
+            tilemap_entity,
+            changes: Vec::new(),
+            resize: None,
+        });
+
+        // Only the state before the first change of a tile matters
+        match pending.changes.iter_mut().find(|x| x.pos == pos) {
+            Some(change) => change.after = after,
+            None => pending.changes.push(TileChange { pos, before, after }),
+        }
+    }
+
+    /// Resizes the tilemap as a step of its own. `removed` has to hold the
+    /// tiles, that the resize is going to despawn, along with their positions.
+    pub fn resize(
+        &mut self,
+        world: &mut World,
+        tilemap_entity: Entity,
+        size: TilemapSize,
+        offset: IVec2,
+        removed: Vec<(TilePos, TileSnapshot)>,
+    ) -> Result<(), EditorError> {
+        let before = resize_tilemap(world, tilemap_entity, size, offset)?;
+
+        self.commit();
+        self.forget_redo(tilemap_entity);
+        self.revision += 1;
+        self.pending = Some(HistoryEntry {
+            tilemap_entity,
+            changes: removed
+                .into_iter()
+                .map(|(pos, tile)| TileChange {
+                    pos,
+                    before: Some(tile),
+                    after: None,
+                })
+                .collect(),
+            resize: Some(SizeChange {
+                before,
+                after: size,
+                offset,
+            }),
+        });
+        self.commit();
+
+        Ok(())
+    }
+
+    pub fn commit(&mut self) {
+        let Some(entry) = self.pending.take() else { return; };
+
+        if self.undo_stack.len() >= MAX_HISTORY_LEN {
+            self.undo_stack.remove(0);
+        }
+
+        self.undo_stack.push(entry);
+    }
+
+    /// Drops all the steps concerning a tilemap. Handy when its contents
+    /// got replaced wholesale.
+    pub fn forget(&mut self, tilemap_entity: Entity) {
+        self.commit();
+        self.revision += 1;
+        self.undo_stack
+            .retain(|x| x.tilemap_entity != tilemap_entity);
+        self.redo_stack
+            .retain(|x| x.tilemap_entity != tilemap_entity);
+    }
+
+    // A new edit makes the undone steps of the tilemap unreachable
+    fn forget_redo(&mut self, tilemap_entity: Entity) {
+        self.redo_stack
+            .retain(|x| x.tilemap_entity != tilemap_entity);
+    }
+
+    /// Changes every time the tiles get edited, undone or replaced
+    /// through the editor.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn can_undo(&self, tilemap_entity: Entity) -> bool {
+        matches!(&self.pending, Some(x) if x.tilemap_entity == tilemap_entity)
+            || self
+                .undo_stack
+                .iter()
+                .any(|x| x.tilemap_entity == tilemap_entity)
+    }
+
+    pub fn can_redo(&self, tilemap_entity: Entity) -> bool {
+        self.redo_stack
+            .iter()
+            .any(|x| x.tilemap_entity == tilemap_entity)
+    }
+
+    /// Undoes the latest step of the tilemap. A step, that doesn't fit
+    /// the tilemap anymore, gets dropped without changing anything.
+    pub fn undo(&mut self, world: &mut World, tilemap_entity: Entity) -> Result<(), EditorError> {
+        self.commit();
+        let Some(idx) = self
+            .undo_stack
+            .iter()
+            .rposition(|x| x.tilemap_entity == tilemap_entity) else { return Ok(()); };
+        let entry = self.undo_stack.remove(idx);
+
+        self.revision += 1;
+        entry.undo(world)?;
+        self.redo_stack.push(entry);
+
+        Ok(())
+    }
+
+    /// Redoes the latest undone step of the tilemap. A step, that doesn't
+    /// fit the tilemap anymore, gets dropped without changing anything.
+    pub fn redo(&mut self, world: &mut World, tilemap_entity: Entity) -> Result<(), EditorError> {
+        self.commit();
+        let Some(idx) = self
+            .redo_stack
+            .iter()
+            .rposition(|x| x.tilemap_entity == tilemap_entity) else { return Ok(()); };
+        let entry = self.redo_stack.remove(idx);
+
+        self.revision += 1;
+        entry.redo(world)?;
+        self.undo_stack.push(entry);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,142 @@
+use bevy::prelude::*;
+use bevy_editor_pls::egui;
+
+use crate::{EditorAction, EditorHoldAction, KeyChord, Modifier, TilemapEditorKeymap};
+
+/// Lets the user rebind the editor shortcuts and keep them in a file
+#[derive(Debug)]
+pub struct KeymapEditor {
+    // The action waiting for the user to press its new chord
+    rebinding: Option<EditorAction>,
+    file_path: String,
+}
+
+impl Default for KeymapEditor {
+    fn default() -> Self {
+        Self {
+            rebinding: None,
+            file_path: "keymap.ron".to_owned(),
+        }
+    }
+}
+
+impl KeymapEditor {
+    /// While rebinding, the pressed keys shouldn't trigger anything
+    pub fn is_rebinding(&self) -> bool {
+        self.rebinding.is_some()
+    }
+
+    /// Returns `true` if the keymap has been changed
+    pub fn ui(&mut self, keymap: &mut TilemapEditorKeymap, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+
+        if let Some(action) = self.rebinding {
+            // The key gets taken out of the input, so it doesn't trigger anything
+            let pressed = ui.input_mut(|x| {
+                let idx = x
+                    .events
+                    .iter()
+                    .position(|event| matches!(event, egui::Event::Key { pressed: true, .. }))?;
+
+                match x.events.remove(idx) {
+                    egui::Event::Key { key, modifiers, .. } => Some((key, modifiers)),
+                    _ => None,
+                }
+            });
+
+            match pressed {
+                // Escape on its own cancels the rebinding
+                Some((egui::Key::Escape, modifiers)) if modifiers.is_none() => {
+                    self.rebinding = None
+                }
+                Some((key, modifiers)) => {
+                    keymap.bind(
+                        action,
+                        Some(KeyChord {
+                            key,
+                            command: modifiers.command,
+                            shift: modifiers.shift,
+                            alt: modifiers.alt,
+                        }),
+                    );
+                    self.rebinding = None;
+                    changed = true;
+                }
+                None => (),
+            }
+        }
+
+        egui::Grid::new("keymap_editor").show(ui, |ui| {
+            for action in EditorAction::ALL {
+                let text = match (self.rebinding == Some(action), keymap.chord(action)) {
+                    (true, _) => "Press a key...".to_owned(),
+                    (false, Some(chord)) => chord.to_string(),
+                    (false, None) => "Unbound".to_owned(),
+                };
+
+                ui.label(action.to_string());
+                if ui.button(text).clicked() {
+                    self.rebinding = Some(action);
+                }
+                if ui.button("Clear").clicked() {
+                    keymap.bind(action, None);
+                    changed = true;
+                }
+                ui.end_row();
+            }
+        });
+
+        ui.separator();
+
+        egui::Grid::new("keymap_editor_holds").show(ui, |ui| {
+            for action in EditorHoldAction::ALL {
+                let mut modifier = keymap.modifier(action);
+                let text = modifier.map_or("Unbound".to_owned(), |x| x.to_string());
+
+                ui.label(action.to_string());
+                egui::ComboBox::from_id_source(action)
+                    .selected_text(text)
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut modifier, None, "Unbound");
+                        for x in Modifier::ALL {
+                            ui.selectable_value(&mut modifier, Some(x), x.to_string());
+                        }
+                    });
+                ui.end_row();
+
+                if modifier != keymap.modifier(action) {
+                    keymap.bind_modifier(action, modifier);
+                    changed = true;
+                }
+            }
+        });
+
+        if ui.button("Reset to defaults").clicked() {
+            *keymap = TilemapEditorKeymap::default();
+            changed = true;
+        }
+
+        ui.text_edit_singleline(&mut self.file_path);
+        ui.horizontal(|ui| {
+            if ui.button("Save").clicked() {
+                match keymap.save(&self.file_path) {
+                    Ok(()) => info!("Saved the keymap to {:?}", self.file_path),
+                    Err(e) => error!("Error: {e}"),
+                }
+            }
+
+            if ui.button("Load").clicked() {
+                match TilemapEditorKeymap::load(&self.file_path) {
+                    Ok(x) => {
+                        info!("Loaded the keymap from {:?}", self.file_path);
+                        *keymap = x;
+                        changed = true;
+                    }
+                    Err(e) => error!("Error: {e}"),
+                }
+            }
+        });
+
+        changed
+    }
+}
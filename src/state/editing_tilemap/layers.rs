@@ -0,0 +1,144 @@
+use bevy::prelude::*;
+use bevy_editor_pls::egui;
+
+use super::SharedStateData;
+
+// Tilemaps closer than that are considered to be at the same place
+const POSITION_EPSILON: f32 = 0.001;
+
+/// A tilemap, that covers the same cells as the edited one.
+struct Layer {
+    entity: Entity,
+    name: String,
+    z: f32,
+    visible: bool,
+}
+
+fn collect_layers(
+    shared: &mut SharedStateData,
+    world: &mut World,
+    tilemap_entity: Entity,
+) -> Vec<Layer> {
+    let queries = shared.query_storage.queries(world);
+    let Ok(current) = queries.tilemap_query.get(world, tilemap_entity) else { return Vec::new(); };
+    let key = (
+        *current.size,
+        *current.grid_size,
+        *current.ty,
+        current.transform.translation().truncate(),
+    );
+
+    let mut layers = queries
+        .tilemap_query
+        .iter(world)
+        .filter(|x| {
+            *x.size == key.0
+                && *x.grid_size == key.1
+                && *x.ty == key.2
+                && x.transform
+                    .translation()
+                    .truncate()
+                    .abs_diff_eq(key.3, POSITION_EPSILON)
+        })
+        .map(|x| Layer {
+            entity: x.entity,
+            name: x.name.map_or("Unnamed tilemap", |x| x.as_str()).to_owned(),
+            z: x.transform.translation().z,
+            visible: !matches!(world.get::<Visibility>(x.entity), Some(Visibility::Hidden)),
+        })
+        .collect::<Vec<_>>();
+
+    // The topmost layer goes first
+    layers.sort_by(|a, b| b.z.total_cmp(&a.z));
+
+    layers
+}
+
+// Puts `upper` right above `lower` by exchanging their z. The layers are sorted
+// by the global z, so that's the one to exchange, even if their parents differ
+fn swap_layers(world: &mut World, upper: Entity, lower: Entity) {
+    let (Some(upper_z), Some(lower_z)) = (
+        world.get::<GlobalTransform>(upper).map(|x| x.translation().z),
+        world.get::<GlobalTransform>(lower).map(|x| x.translation().z),
+    ) else {
+        return;
+    };
+
+    // Layers at the same height need to be pulled apart
+    let (new_upper_z, new_lower_z) = match upper_z == lower_z {
+        true => (upper_z, upper_z - 1.0),
+        false => (lower_z, upper_z),
+    };
+
+    move_layer(world, upper, new_upper_z.max(new_lower_z) - upper_z);
+    move_layer(world, lower, new_upper_z.min(new_lower_z) - lower_z);
+}
+
+// Moves the tilemap along the global z by `delta`, taking its parent into account
+fn move_layer(world: &mut World, entity: Entity, delta: f32) {
+    let scale = world
+        .get::<Parent>(entity)
+        .and_then(|x| world.get::<GlobalTransform>(x.get()))
+        .map_or(1.0, |x| x.affine().matrix3.z_axis.z);
+
+    if scale.abs() < f32::EPSILON {
+        return;
+    }
+    if let Some(mut transform) = world.get_mut::<Transform>(entity) {
+        transform.translation.z += delta / scale;
+    }
+}
+
+/// Lists the tilemaps stacked together with the edited one.
+/// Returns the layer the user wants to edit instead.
+pub fn layers_ui(
+    shared: &mut SharedStateData,
+    world: &mut World,
+    ui: &mut egui::Ui,
+    tilemap_entity: Entity,
+) -> Option<Entity> {
+    let layers = collect_layers(shared, world, tilemap_entity);
+    let mut pick = None;
+
+    ui.label("Layers");
+
+    for (idx, layer) in layers.iter().enumerate() {
+        ui.horizontal(|ui| {
+            if ui
+                .selectable_label(layer.entity == tilemap_entity, &layer.name)
+                .clicked()
+                && layer.entity != tilemap_entity
+            {
+                pick = Some(layer.entity);
+            }
+
+            let mut visible = layer.visible;
+            if ui.checkbox(&mut visible, "Visible").changed() {
+                world.entity_mut(layer.entity).insert(match visible {
+                    true => Visibility::Inherited,
+                    false => Visibility::Hidden,
+                });
+            }
+
+            let mut locked = shared.locked_layers.contains(&layer.entity);
+            if ui.checkbox(&mut locked, "Locked").changed() {
+                match locked {
+                    true => shared.locked_layers.insert(layer.entity),
+                    false => shared.locked_layers.remove(&layer.entity),
+                };
+            }
+
+            if ui.add_enabled(idx > 0, egui::Button::new("Up")).clicked() {
+                swap_layers(world, layer.entity, layers[idx - 1].entity);
+            }
+            if ui
+                .add_enabled(idx + 1 < layers.len(), egui::Button::new("Down"))
+                .clicked()
+            {
+                swap_layers(world, layers[idx + 1].entity, layer.entity);
+            }
+        });
+    }
+
+    pick
+}
@@ -1,39 +1,44 @@
 use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
 
 use bevy::prelude::*;
 use bevy_ecs_tilemap::prelude::*;
 use bevy_editor_pls::{egui, egui_dock};
-use bevy_egui::EguiUserTextures;
 
 use crate::{
-    coord_utils::{bevy_to_egui, gridify_int},
+    format::{TilemapData, TilemapFormats},
     tile_data::TileData,
-    EditorTileDataRegistry,
+    EditorAction, EditorHoldAction, EditorTileDataRegistry, TilemapEditorKeymap,
 };
 
 use self::{
-    palette::TilePalette,
-    tools::{TileEraser, TilePainter, TilePicker, TileProperties, TileWhoIs, Tool, ToolContext},
+    grid::GridOverlay, history::TileSnapshot, keymap_editor::KeymapEditor,
+    other_tilemaps::OtherTilemaps, palette::TilePalette, resize::ResizePanel, stroke::BrushStroke,
+    tileset::Tileset, tools::TilePicker,
 };
 
 use super::{EditorError, Message, SharedStateData};
-
+use crate::TilemapFormat;
+
+pub(super) use self::clipboard::TileStamp;
+pub(super) use self::history::EditHistory;
+pub(crate) use self::tools::EditorTools;
+pub use self::tools::{TileProperties, Tool, ToolContext};
+
+mod clipboard;
+mod grid;
+mod history;
+mod keymap_editor;
+mod layers;
+mod other_tilemaps;
 mod palette;
+mod resize;
+mod stroke;
+mod tileset;
 mod tools;
 
-// The y component is computed differently, so the higher you go,
-// the bigger y component of the result gets.
-fn global_pos_to_local(pos: egui::Pos2, tilemap_rect: egui::Rect) -> Option<egui::Pos2> {
-    let tilemap_size = tilemap_rect.size();
-    let pos = egui::pos2(pos.x - tilemap_rect.min.x, tilemap_rect.max.y - pos.y);
-
-    if pos.x <= 0.0 || pos.y <= 0.0 || pos.x >= tilemap_size.x || pos.y >= tilemap_size.y {
-        return None;
-    }
-
-    Some(pos)
-}
-
 mod flip_rotation {
     use bevy_ecs_tilemap::tiles::TileFlip;
 
@@ -142,14 +147,21 @@ mod flip_rotation {
 
 pub(super) struct StateData {
     // editor state stuff
-    tools: [Box<dyn Tool>; 4],
-    current_tool: usize,
     palette_state: TileProperties,
+    brush_size: UVec2,
+    file_path: String,
+    current_format: usize,
+    tileset: Tileset,
+    other_tilemaps: OtherTilemaps,
+    grid: GridOverlay,
+    resize_panel: ResizePanel,
+    keymap_editor: KeymapEditor,
+    stroke: BrushStroke,
+    selection: Option<(TilePos, TilePos)>,
+    // The clipboard contents picked up for pasting
+    stamp: Option<TileStamp>,
     // bevy stuff
-    tilemap_texture: Handle<Image>,
     tilemap_entity: Entity,
-    // egui stuff
-    tilemap_texture_egui: egui::TextureId,
 }
 
 impl StateData {
@@ -160,56 +172,52 @@ impl StateData {
     ) -> Result<Self, EditorError> {
         let queries = shared_data.query_storage.queries(world);
 
-        // Extract the atlas image and register it
-        // FIXME this solution supports only single-image atlases
+        // Register the tileset images
         // TODO do more tilemap diagnostics
-        let texture = queries
+        let (texture, size) = queries
             .tilemap_query
             .get(world, tilemap_entity)
+            .map(|x| (x.texture.clone(), *x.size))
             .map_err(|query_error| EditorError::BadTilemapEntity {
                 tilemap_entity,
                 query_error,
-            })?
-            .texture
-            .clone();
-        let (tilemap_texture, tilemap_texture_egui) = match texture {
-            TilemapTexture::Single(x) => (
-                x.clone(),
-                world.resource_mut::<EguiUserTextures>().add_image(x),
-            ),
-            // FIXME needs careful tweaking due to "atlas feature"
-            TilemapTexture::Vector(_) => {
-                return Err(EditorError::UnsupportedTilemapTextureType("Vector"))
-            }
-            TilemapTexture::TextureContainer(_) => {
-                return Err(EditorError::UnsupportedTilemapTextureType(
-                    "TextureContainer",
-                ))
-            }
-        };
+            })?;
+        let tileset = Tileset::new(&texture, world)?;
+
+        if shared_data.tools.is_empty() {
+            shared_data.tools = world.resource::<EditorTools>().create();
+        }
+        for tool in &mut shared_data.tools {
+            tool.tilemap_changed();
+        }
 
         Ok(Self {
             // editor state stuff
-            tools: [
-                Box::new(TilePainter),
-                Box::new(TileEraser),
-                Box::new(TilePicker),
-                Box::new(TileWhoIs),
-            ],
-            current_tool: 0,
             palette_state: TileProperties::default(),
+            brush_size: UVec2::ONE,
+            file_path: world
+                .resource::<TilemapFormats>()
+                .0
+                .first()
+                .map(|x| format!("tilemap.{}", x.extension()))
+                .unwrap_or_default(),
+            current_format: 0,
+            tileset,
+            other_tilemaps: OtherTilemaps::default(),
+            grid: GridOverlay::default(),
+            resize_panel: ResizePanel::new(size),
+            keymap_editor: KeymapEditor::default(),
+            stroke: BrushStroke::default(),
+            selection: None,
+            stamp: None,
             // bevy stuff
-            tilemap_texture,
             tilemap_entity,
-            // egui stuff
-            tilemap_texture_egui,
         })
     }
 
-    pub fn cleanup(self, world: &mut World) {
-        world
-            .resource_mut::<EguiUserTextures>()
-            .remove_image(&self.tilemap_texture);
+    pub fn cleanup(mut self, world: &mut World) {
+        self.other_tilemaps.restore(world);
+        self.tileset.cleanup(world);
     }
 
     pub fn ui(
@@ -220,11 +228,17 @@ impl StateData {
     ) -> Message {
         let queries = shared.query_storage.queries(world);
         let tile_data = world.resource::<EditorTileDataRegistry>().clone();
-        let mut lock = tile_data.lock();
+        let mut keymap = world.resource::<TilemapEditorKeymap>().clone();
+
+        let egui_ctx = ui.ctx().clone();
+        let rebinding = self.keymap_editor.is_rebinding();
+
+        // The keys pressed while rebinding belong to the keymap editor
+        let pressed = |action| !rebinding && keymap.pressed(action, &egui_ctx);
 
         // Fetch some info about the tilemap and its atlas
-        let (tile_size, texture) = match queries.tilemap_query.get(world, self.tilemap_entity) {
-            Ok(x) => (x.tile_size.into(), x.texture.clone()),
+        let (layout, texture, size) = match queries.tilemap_query.get(world, self.tilemap_entity) {
+            Ok(x) => (self.tileset.layout(&x, world), x.texture.clone(), *x.size),
             Err(query_error) => {
                 return Message::ShowErrorAndExitEditing(EditorError::BadTilemapEntity {
                     tilemap_entity: self.tilemap_entity,
@@ -232,13 +246,9 @@ impl StateData {
                 })
             }
         };
-        let atlas_size = match world.resource::<Assets<Image>>().get(&self.tilemap_texture) {
-            Some(x) => x.size(),
-            None => {
-                return Message::ShowErrorAndExitEditing(EditorError::InvalidImageHandle {
-                    handle: self.tilemap_texture.clone_weak(),
-                })
-            }
+        let layout = match layout {
+            Ok(x) => x,
+            Err(e) => return Message::ShowErrorAndExitEditing(e),
         };
 
         if ui.button("Exit").clicked() {
@@ -247,21 +257,126 @@ impl StateData {
 
         ui.separator();
 
+        // Nothing can change a locked layer, its history included
+        let locked = shared.locked_layers.contains(&self.tilemap_entity);
+        let (mut undo, mut redo) = (
+            !locked && pressed(EditorAction::Undo),
+            !locked && pressed(EditorAction::Redo),
+        );
+        ui.horizontal(|ui| {
+            undo |= ui
+                .add_enabled(
+                    !locked && shared.history.can_undo(self.tilemap_entity),
+                    egui::Button::new("Undo"),
+                )
+                .clicked();
+            redo |= ui
+                .add_enabled(
+                    !locked && shared.history.can_redo(self.tilemap_entity),
+                    egui::Button::new("Redo"),
+                )
+                .clicked();
+        });
+
+        let res = if undo {
+            shared.history.undo(world, self.tilemap_entity)
+        } else if redo {
+            shared.history.redo(world, self.tilemap_entity)
+        } else {
+            Ok(())
+        };
+
+        if let Err(e) = res {
+            error!("Error: {e}");
+        }
+
+        ui.separator();
+
+        if let Some(layer) = layers::layers_ui(shared, world, ui, self.tilemap_entity) {
+            return Message::EditTilemap(layer);
+        }
+
+        self.other_tilemaps.ui(ui);
+
+        let queries = shared.query_storage.queries(world);
+        self.other_tilemaps
+            .update(world, queries.tilemap_query, self.tilemap_entity);
+
+        ui.separator();
+
+        self.grid.ui(ui);
+
+        ui.separator();
+
+        let resize = ui
+            .collapsing("Resize", |ui| self.resize_panel.ui(ui, size, locked))
+            .body_returned
+            .unwrap_or(false);
+
+        if resize {
+            match self.resize_tilemap(shared, world) {
+                Ok(()) => {
+                    self.selection = None;
+                    info!("Resized the tilemap");
+                }
+                Err(e) => error!("Error: {e}"),
+            }
+        }
+
+        ui.separator();
+
+        let tool_count = shared.tools.len();
+
+        if pressed(EditorAction::NextTool) {
+            shared.current_tool = (shared.current_tool + 1) % tool_count;
+        }
+        if pressed(EditorAction::PreviousTool) {
+            shared.current_tool = (shared.current_tool + tool_count - 1) % tool_count;
+        }
+        if let Some(id) = (0..tool_count.min(u8::MAX as usize))
+            .find(|id| pressed(EditorAction::SelectTool(*id as u8)))
+        {
+            shared.current_tool = id;
+        }
+
         ui.horizontal(|ui| {
-            self.tools.iter().enumerate().for_each(|(id, tool)| {
-                ui.selectable_value(&mut self.current_tool, id, tool.name());
+            shared.tools.iter().enumerate().for_each(|(id, tool)| {
+                let text = match tool.icon() {
+                    Some(icon) => format!("{icon} {}", tool.name()),
+                    None => tool.name().to_owned(),
+                };
+
+                ui.selectable_value(&mut shared.current_tool, id, text);
             })
         });
+        shared.tools[shared.current_tool].settings_ui(ui);
 
         ui.separator();
 
-        ui.label(format!("Tile texture ID: {}", self.palette_state.texture.0));
-        ui.add(TilePalette::new(
-            &mut self.palette_state.texture.0,
-            bevy_to_egui(atlas_size),
-            bevy_to_egui(tile_size),
-            self.tilemap_texture_egui,
+        ui.label(format!(
+            "Tile texture ID: {} ({}x{} tiles)",
+            self.palette_state.texture.0, self.brush_size.x, self.brush_size.y
         ));
+        let palette_step = [
+            (EditorAction::PaletteLeft, IVec2::NEG_X),
+            (EditorAction::PaletteRight, IVec2::X),
+            // The palette's rows go from top to bottom
+            (EditorAction::PaletteUp, IVec2::NEG_Y),
+            (EditorAction::PaletteDown, IVec2::Y),
+        ]
+        .into_iter()
+        .filter(|(action, _)| pressed(*action))
+        .map(|(_, step)| step)
+        .sum();
+        ui.add(
+            TilePalette::new(
+                &mut self.palette_state.texture.0,
+                &mut self.brush_size,
+                layout,
+                &self.tileset,
+            )
+            .step(palette_step),
+        );
 
         ui.separator();
 
@@ -286,27 +401,271 @@ impl StateData {
 
         ui.separator();
 
-        self.tile_props_ui(lock.access_tileset_data(texture), world, ui);
+        self.tile_props_ui(tile_data.lock().access_tileset_data(texture), world, ui);
+
+        ui.separator();
 
-        // TODO make the keys configurable
-        if ui.input(|x| x.key_pressed(egui::Key::H)) {
-            self.palette_state.flip.x = !self.palette_state.flip.x;
+        let msg = self.file_ui(shared, world, ui);
+
+        ui.separator();
+
+        // A held stamp takes these keys for itself
+        if self.stamp.is_none() {
+            if pressed(EditorAction::FlipX) {
+                self.palette_state.flip.x = !self.palette_state.flip.x;
+            }
+            if pressed(EditorAction::FlipY) {
+                self.palette_state.flip.y = !self.palette_state.flip.y;
+            }
+            if pressed(EditorAction::RotateClockwise) {
+                flip_rotation::rotate_minus_90(&mut self.palette_state.flip);
+            }
+            if pressed(EditorAction::RotateCounterclockwise) {
+                flip_rotation::rotate_plus_90(&mut self.palette_state.flip);
+            }
+            if pressed(EditorAction::FlipDiagonal) {
+                self.palette_state.flip.d = !self.palette_state.flip.d;
+            }
         }
-        if ui.input(|x| x.key_pressed(egui::Key::V)) {
-            self.palette_state.flip.y = !self.palette_state.flip.y;
+
+        let keymap_changed = ui
+            .collapsing("Keyboard shortcuts", |ui| {
+                self.keymap_editor.ui(&mut keymap, ui)
+            })
+            .body_returned
+            .unwrap_or(false);
+
+        if keymap_changed {
+            world.insert_resource(keymap);
         }
-        if ui.input(|x| x.key_pressed(egui::Key::D)) {
-            self.palette_state.flip.d = !self.palette_state.flip.d;
+
+        msg
+    }
+
+    fn file_ui(
+        &mut self,
+        shared: &mut SharedStateData,
+        world: &mut World,
+        ui: &mut egui::Ui,
+    ) -> Message {
+        let mut msg = Message::None;
+        let formats = world.resource::<TilemapFormats>().clone();
+        let old_format = self.current_format;
+
+        ui.label("File");
+        egui::ComboBox::from_label("Format")
+            .selected_text(
+                formats
+                    .0
+                    .get(self.current_format)
+                    .map_or("None", |x| x.name()),
+            )
+            .show_ui(ui, |ui| {
+                formats.0.iter().enumerate().for_each(|(id, format)| {
+                    ui.selectable_value(&mut self.current_format, id, format.name());
+                })
+            });
+        ui.text_edit_singleline(&mut self.file_path);
+
+        if old_format != self.current_format {
+            self.file_path = Path::new(&self.file_path)
+                .with_extension(formats.0[self.current_format].extension())
+                .to_string_lossy()
+                .into_owned();
         }
-        if ui.input(|x| x.key_pressed(egui::Key::R)) {
-            if ui.input(|x| x.modifiers.shift) {
-                flip_rotation::rotate_plus_90(&mut self.palette_state.flip);
-            } else {
-                flip_rotation::rotate_minus_90(&mut self.palette_state.flip);
+
+        ui.horizontal(|ui| {
+            if ui.button("Save").clicked() {
+                match self.save_tilemap(shared, world) {
+                    Ok(()) => info!("Saved the tilemap to {:?}", self.file_path),
+                    Err(e) => error!("Error: {e}"),
+                }
+            }
+
+            if ui
+                .add_enabled(
+                    !shared.locked_layers.contains(&self.tilemap_entity),
+                    egui::Button::new("Load"),
+                )
+                .clicked()
+            {
+                match self.load_tilemap(shared, world) {
+                    Ok(()) => info!("Loaded the tilemap from {:?}", self.file_path),
+                    Err(e) => error!("Error: {e}"),
+                }
             }
+
+            if ui.button("Load as new tilemap").clicked() {
+                match self.load_new_tilemap(shared, world) {
+                    Ok(tilemap_entity) => msg = Message::EditTilemap(tilemap_entity),
+                    Err(e) => error!("Error: {e}"),
+                }
+            }
+        });
+
+        msg
+    }
+
+    fn resize_tilemap(
+        &self,
+        shared: &mut SharedStateData,
+        world: &mut World,
+    ) -> Result<(), EditorError> {
+        let queries = shared.query_storage.queries(world);
+        let tile_data = world.resource::<EditorTileDataRegistry>().clone();
+        let mut lock = tile_data.lock();
+        let tilemap = queries
+            .tilemap_query
+            .get(world, self.tilemap_entity)
+            .map_err(|query_error| EditorError::BadTilemapEntity {
+                tilemap_entity: self.tilemap_entity,
+                query_error,
+            })?;
+        let old_size = *tilemap.size;
+        let size = self.resize_panel.size();
+        let offset = self.resize_panel.offset(old_size);
+        let tile_data = lock.access_tileset_data(tilemap.texture.clone());
+
+        // The tiles, that won't fit, have to be remembered for undoing
+        let removed = tilemap
+            .storage
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, tile_entity)| {
+                let idx = idx as u32;
+                let pos = TilePos {
+                    x: idx % old_size.x,
+                    y: idx / old_size.x,
+                };
+                let new_pos = IVec2::new(pos.x as i32, pos.y as i32) + offset;
+
+                if TilePos::from_i32_pair(new_pos.x, new_pos.y, &size).is_some() {
+                    return None;
+                }
+
+                let tile = world.get_entity((*tile_entity)?)?;
+
+                TileSnapshot::capture(tile, tile_data).map(|x| (pos, x))
+            })
+            .collect();
+
+        shared
+            .history
+            .resize(world, self.tilemap_entity, size, offset, removed)
+    }
+
+    fn save_tilemap(
+        &self,
+        shared: &mut SharedStateData,
+        world: &mut World,
+    ) -> Result<(), EditorError> {
+        let queries = shared.query_storage.queries(world);
+        let tile_data = world.resource::<EditorTileDataRegistry>().clone();
+        let mut lock = tile_data.lock();
+        let texture = queries
+            .tilemap_query
+            .get(world, self.tilemap_entity)
+            .map_err(|query_error| EditorError::BadTilemapEntity {
+                tilemap_entity: self.tilemap_entity,
+                query_error,
+            })?
+            .texture
+            .clone();
+        let data = TilemapData::extract(
+            world,
+            self.tilemap_entity,
+            queries.tilemap_query,
+            queries.tile_query,
+            lock.access_tileset_data(texture),
+        )?;
+        let registry = world.resource::<AppTypeRegistry>().read();
+
+        self.format(world)?
+            .write(&data, &registry, &mut File::create(&self.file_path)?)
+    }
+
+    fn format(&self, world: &World) -> Result<Arc<dyn TilemapFormat>, EditorError> {
+        world
+            .resource::<TilemapFormats>()
+            .0
+            .get(self.current_format)
+            .cloned()
+            .ok_or(EditorError::NoTilemapFormats)
+    }
+
+    fn load_tilemap(
+        &mut self,
+        shared: &mut SharedStateData,
+        world: &mut World,
+    ) -> Result<(), EditorError> {
+        let registry = world.resource::<AppTypeRegistry>().clone();
+        let registry = registry.read();
+        let data = self
+            .format(world)?
+            .read(&registry, &mut File::open(&self.file_path)?)?;
+        let queries = shared.query_storage.queries(world);
+
+        data.write_into(world, self.tilemap_entity, queries.tilemap_query, &registry)?;
+        shared.history.forget(self.tilemap_entity);
+        // The old selection might not fit the new size
+        self.selection = None;
+        self.stamp = None;
+
+        Ok(())
+    }
+
+    fn load_new_tilemap(
+        &self,
+        shared: &mut SharedStateData,
+        world: &mut World,
+    ) -> Result<Entity, EditorError> {
+        let registry = world.resource::<AppTypeRegistry>().clone();
+        let registry = registry.read();
+        let data = self
+            .format(world)?
+            .read(&registry, &mut File::open(&self.file_path)?)?;
+        let queries = shared.query_storage.queries(world);
+
+        // Whatever the file doesn't specify gets taken from the current tilemap
+        let (map_type, texture) = match queries.tilemap_query.get(world, self.tilemap_entity) {
+            Ok(x) => (*x.ty, x.texture.clone()),
+            Err(query_error) => {
+                return Err(EditorError::BadTilemapEntity {
+                    tilemap_entity: self.tilemap_entity,
+                    query_error,
+                })
+            }
+        };
+        let texture = match &data.texture {
+            Some(x) => x.load(world.resource::<AssetServer>()),
+            None => texture,
+        };
+        let name = Path::new(&self.file_path)
+            .file_stem()
+            .map(|x| x.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let tilemap_entity = world
+            .spawn((
+                TilemapBundle {
+                    grid_size: data.grid_size,
+                    map_type,
+                    size: data.size,
+                    storage: TileStorage::empty(data.size),
+                    texture,
+                    tile_size: data.tile_size,
+                    ..default()
+                },
+                Name::new(name),
+            ))
+            .id();
+
+        if let Err(e) = data.write_into(world, tilemap_entity, queries.tilemap_query, &registry) {
+            world.entity_mut(tilemap_entity).despawn_recursive();
+
+            return Err(e);
         }
 
-        Message::None
+        Ok(tilemap_entity)
     }
 
     fn tile_props_ui(
@@ -315,7 +674,7 @@ impl StateData {
         world: &mut World,
         ui: &mut egui::Ui,
     ) {
-        let Some(tile_data) = tile_data.get_mut(&self.palette_state.texture.0) else { return };
+        let Some(tile_data) = tile_data.get_mut(&self.palette_state.texture.0) else { return; };
 
         tile_data.values_mut().for_each(|value| {
             let heading = value.type_name();
@@ -335,6 +694,11 @@ impl StateData {
         let queries = shared.query_storage.queries(world);
         let tile_data = world.resource::<EditorTileDataRegistry>().clone();
         let mut lock = tile_data.lock();
+        // The keys pressed while rebinding belong to the keymap editor
+        let keymap = match self.keymap_editor.is_rebinding() {
+            true => TilemapEditorKeymap::unbound(),
+            false => world.resource::<TilemapEditorKeymap>().clone(),
+        };
 
         // FIXME the clipping has been improved, but the frames
         // still paint themselves on top of other widgets
@@ -347,9 +711,7 @@ impl StateData {
 
         // Fetch information about the tilemap and the cursor
         // TODO consider introducing a user-friendly reaction to the absense of editor camera
-        let Some(cam) = queries.camera_query.iter(world)
-            .find(|x| x.is_active())
-        else {
+        let Some(cam) = queries.camera_query.iter(world).find(|x| x.is_active()) else {
             return Message::None;
         };
         let tilemap = match queries.tilemap_query.get(world, self.tilemap_entity) {
@@ -362,55 +724,102 @@ impl StateData {
             }
         };
 
-        // Compute the display rects
-        let ref_points = cam.tilemap_points(viewport_rect, &tilemap);
-        let grid_sample_rect = ref_points.grid_sample_rect();
-        let tilemap_rect = ref_points.tilemap_rect();
+        // Figure out where the tilemap is on the screen
+        let Some(ref_points) = cam.tilemap_points(viewport_rect, &tilemap) else {
+            return Message::None;
+        };
 
         // Paint a frame around the whole tilemap
-        painter.rect_stroke(
-            tilemap_rect,
-            0.0,
+        painter.add(egui::Shape::closed_line(
+            ref_points.tilemap_outline(),
             egui::Stroke::new(2.0, egui::Color32::RED),
-        );
+        ));
+        self.grid.paint(&ref_points, &painter);
 
         let hovered_tile = ui
             .input(|x| x.pointer.hover_pos())
-            .and_then(|p| global_pos_to_local(p, tilemap_rect))
-            .map(|p| gridify_int(p, grid_sample_rect.size()))
+            .and_then(|p| ref_points.hovered_tile(p))
             .filter(|_| ui.ui_contains_pointer());
+        let (pressed, down) = ui.input(|x| {
+            (
+                x.pointer.button_pressed(egui::PointerButton::Primary),
+                x.pointer.button_down(egui::PointerButton::Primary),
+            )
+        });
+        self.stroke.update(hovered_tile, pressed, down);
 
-        ui.label(format!("Tool: {}", self.tools[self.current_tool].name()));
+        if let Some((a, b)) = self.selection {
+            painter.add(egui::Shape::closed_line(
+                ref_points.region_outline(a, b),
+                egui::Stroke::new(1.0, egui::Color32::YELLOW),
+            ));
+        }
 
-        match hovered_tile {
+        let locked = shared.locked_layers.contains(&self.tilemap_entity);
+        let alt = keymap.held(EditorHoldAction::PickTile, ui.ctx());
+        let mut ctx = ToolContext::new(
+            world,
+            ref_points,
+            self.tilemap_entity,
+            &self.tileset,
+            queries.tile_query,
+            queries.tilemap_query,
+            lock.access_tileset_data(tilemap.texture.clone()),
+            &mut self.palette_state,
+            &mut self.brush_size,
+            &mut shared.history,
+            &mut shared.clipboard,
+            &mut self.selection,
+            &keymap,
+            &self.stroke,
+        );
+
+        let res = clipboard::clipboard_shortcuts(&mut ctx, &mut self.stamp, locked, ui);
+
+        // Holding Alt (by default) turns any tool into the picker for a moment
+        let mut alt_picker = TilePicker;
+        let tool: &mut dyn Tool = match alt {
+            true => &mut alt_picker,
+            false => shared.tools[shared.current_tool].as_mut(),
+        };
+
+        match (&self.stamp, alt) {
+            (Some(_), false) => ui.label("Tool: Stamp"),
+            _ => ui.label(format!("Tool: {}", tool.name())),
+        };
+
+        let res = res.and_then(|()| match hovered_tile {
+            Some(_) if locked => {
+                ui.label("The layer is locked");
+
+                Ok(())
+            }
             Some(hovered_tile) => {
                 ui.label(format!("Pos: {} {}", hovered_tile.x, hovered_tile.y));
 
-                let res = self.tools[self.current_tool].viewport_ui(
-                    &mut ToolContext::new(
-                        world,
-                        ref_points,
-                        self.tilemap_entity,
-                        self.tilemap_texture_egui,
-                        queries.tile_query,
-                        queries.tilemap_query,
-                        lock.access_tileset_data(tilemap.texture.clone()),
-                        &mut self.palette_state,
-                    ),
-                    hovered_tile.into(),
-                    ui,
-                    &painter,
-                );
-
-                if let Err(e @ EditorError::BadTilemapEntity { .. }) = res {
-                    error!("Error: {e}");
-
-                    return Message::StartPickingTilemap;
+                match (&self.stamp, alt) {
+                    (Some(stamp), false) => {
+                        clipboard::stamp_ui(stamp, &mut ctx, hovered_tile, ui, &painter)
+                    }
+                    _ => tool.viewport_ui(&mut ctx, hovered_tile, ui, &painter),
                 }
             }
             None => {
                 ui.label("Pos: out of bounds");
+
+                Ok(())
             }
+        });
+
+        if let Err(e @ EditorError::BadTilemapEntity { .. }) = res {
+            error!("Error: {e}");
+
+            return Message::StartPickingTilemap;
+        }
+
+        // A whole stroke goes into the history as a single step
+        if !self.stroke.is_active() {
+            shared.history.commit();
         }
 
         Message::None
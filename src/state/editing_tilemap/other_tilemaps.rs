@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use bevy_editor_pls::egui;
+
+use crate::queries::TilemapQuery;
+
+// The opacity, that dimmed tilemaps are rendered at
+const DIM_ALPHA: f32 = 0.25;
+
+/// How the tilemaps other than the edited one get displayed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OthersMode {
+    #[default]
+    Shown,
+    Hidden,
+    Dimmed,
+}
+
+// What a tilemap looked like before the editor touched it. The tile colors
+// are kept along with the dimmed ones, so the tiles changed in the meantime
+// can be told apart.
+#[derive(Debug)]
+enum SavedLook {
+    Visibility(Option<Visibility>),
+    TileColors(Vec<(Entity, TileColor, TileColor)>),
+}
+
+/// Hides or dims the tilemaps, that aren't being edited, and keeps
+/// their original look around to restore it later.
+#[derive(Debug, Default)]
+pub struct OtherTilemaps {
+    pub mode: OthersMode,
+    applied_mode: OthersMode,
+    saved: HashMap<Entity, SavedLook>,
+}
+
+impl OtherTilemaps {
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Other tilemaps");
+            ui.selectable_value(&mut self.mode, OthersMode::Shown, "Shown");
+            ui.selectable_value(&mut self.mode, OthersMode::Hidden, "Hidden");
+            ui.selectable_value(&mut self.mode, OthersMode::Dimmed, "Dimmed");
+        });
+    }
+
+    /// Brings the tilemaps in line with the current mode. Tilemaps, that
+    /// have appeared since the last call, get affected too.
+    pub fn update(
+        &mut self,
+        world: &mut World,
+        tilemap_query: &mut QueryState<TilemapQuery>,
+        tilemap_entity: Entity,
+    ) {
+        if self.mode != self.applied_mode {
+            self.restore(world);
+            self.applied_mode = self.mode;
+        }
+
+        if self.mode == OthersMode::Shown {
+            return;
+        }
+
+        let others = tilemap_query
+            .iter(world)
+            .filter(|x| x.entity != tilemap_entity && !self.saved.contains_key(&x.entity))
+            .map(|x| {
+                (
+                    x.entity,
+                    x.storage.iter().flatten().copied().collect::<Vec<_>>(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        for (entity, tiles) in others {
+            let look = match self.mode {
+                OthersMode::Shown => unreachable!(),
+                OthersMode::Hidden => {
+                    let visibility = world.get::<Visibility>(entity).copied();
+
+                    world.entity_mut(entity).insert(Visibility::Hidden);
+
+                    SavedLook::Visibility(visibility)
+                }
+                OthersMode::Dimmed => SavedLook::TileColors(
+                    tiles
+                        .into_iter()
+                        .filter_map(|tile| {
+                            let mut color = world.get_mut::<TileColor>(tile)?;
+                            let old = *color;
+                            let alpha = old.0.a();
+
+                            color.0.set_a(alpha * DIM_ALPHA);
+
+                            Some((tile, old, *color))
+                        })
+                        .collect(),
+                ),
+            };
+
+            self.saved.insert(entity, look);
+        }
+    }
+
+    /// Puts everything back the way it was. Entities, that have been
+    /// despawned or changed by something else in the meantime, are skipped.
+    pub fn restore(&mut self, world: &mut World) {
+        for (entity, look) in self.saved.drain() {
+            match look {
+                SavedLook::Visibility(visibility) => {
+                    let Some(mut entity) = world.get_entity_mut(entity) else { continue; };
+
+                    if !matches!(entity.get::<Visibility>(), Some(Visibility::Hidden)) {
+                        continue;
+                    }
+
+                    match visibility {
+                        Some(x) => entity.insert(x),
+                        None => entity.remove::<Visibility>(),
+                    };
+                }
+                SavedLook::TileColors(colors) => {
+                    for (tile, old, dimmed) in colors {
+                        match world.get_mut::<TileColor>(tile) {
+                            Some(mut color) if color.0 == dimmed.0 => *color = old,
+                            _ => (),
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,27 +1,53 @@
 use bevy::prelude::*;
 use bevy_editor_pls::egui;
 
-use crate::coord_utils::{gridify_int, int_tile_pos_to_id, tile_id_to_pos};
+use super::tileset::Tileset;
+use crate::coord_utils::{int_tile_pos_to_id, AtlasLayout};
 
+/// The atlas viewer. Clicking a tile selects it, while dragging
+/// selects a whole block of tiles, starting at `selected_tile`.
+/// The selection can also be moved around by whole tiles.
 pub struct TilePalette<'a> {
     selected_tile: &'a mut u32,
-    palette_size: egui::Vec2,
-    tile_size: egui::Vec2,
-    palette_texture: egui::TextureId,
+    selection_size: &'a mut UVec2,
+    layout: AtlasLayout,
+    tileset: &'a Tileset,
+    step: IVec2,
 }
 
 impl<'a> TilePalette<'a> {
     pub fn new(
         selected_tile: &'a mut u32,
-        palette_size: egui::Vec2,
-        tile_size: egui::Vec2,
-        palette_texture: egui::TextureId,
+        selection_size: &'a mut UVec2,
+        layout: AtlasLayout,
+        tileset: &'a Tileset,
     ) -> Self {
         Self {
             selected_tile,
-            palette_size,
-            tile_size,
-            palette_texture,
+            selection_size,
+            layout,
+            tileset,
+            step: IVec2::ZERO,
+        }
+    }
+
+    /// Moves the selection by `step` tiles. The selection stops at the edges.
+    pub fn step(mut self, step: IVec2) -> Self {
+        self.step = step;
+
+        self
+    }
+
+    fn apply_step(&mut self) {
+        let max = self.layout.size_in_tiles().as_ivec2() - self.selection_size.as_ivec2();
+        let pos = self.tile_id_to_int_pos(*self.selected_tile).as_ivec2() + self.step;
+        let pos = pos.clamp(IVec2::ZERO, max.max(IVec2::ZERO)).as_uvec2();
+
+        if !self.block_fits(pos, *self.selection_size) {
+            return;
+        }
+        if let Some(id) = int_tile_pos_to_id(pos, self.layout.size_in_tiles()) {
+            *self.selected_tile = id;
         }
     }
 
@@ -41,17 +67,29 @@ impl<'a> TilePalette<'a> {
         global_pos - palette_response.inner_rect.min.to_vec2() + palette_response.state.offset
     }
 
-    fn palette_size_in_tiles(&self) -> UVec2 {
-        UVec2::new(
-            (self.palette_size.x / self.tile_size.x) as u32,
-            (self.palette_size.y / self.tile_size.y) as u32,
-        )
+    fn tile_id_to_int_pos(&self, tile_id: u32) -> UVec2 {
+        let tiles_per_line = self.layout.size_in_tiles().x.max(1);
+
+        UVec2::new(tile_id % tiles_per_line, tile_id / tiles_per_line)
     }
 
-    fn tile_in_bounds(&self) -> bool {
-        let pos = tile_id_to_pos(*self.selected_tile, self.palette_size, self.tile_size);
+    // Whether the block is within the palette. The last row of separate
+    // images might not be full, and its empty cells can't be picked either.
+    fn block_fits(&self, first_tile: UVec2, size: UVec2) -> bool {
+        let end = first_tile + size;
+        let palette_size_in_tiles = self.layout.size_in_tiles();
+        let last_id = (end.y - 1) * palette_size_in_tiles.x + end.x - 1;
 
-        pos.x < self.palette_size.x && pos.y < self.palette_size.y
+        end.x <= palette_size_in_tiles.x
+            && end.y <= palette_size_in_tiles.y
+            && !matches!(self.tileset.tile_count(), Some(count) if last_id >= count)
+    }
+
+    fn selection_in_bounds(&self) -> bool {
+        self.block_fits(
+            self.tile_id_to_int_pos(*self.selected_tile),
+            *self.selection_size,
+        )
     }
 
     fn paint_tile_picker<R>(
@@ -59,12 +97,18 @@ impl<'a> TilePalette<'a> {
         palette_response: &egui::scroll_area::ScrollAreaOutput<R>,
         painter: &egui::Painter,
         tile_id: u32,
+        size: UVec2,
     ) {
-        let local_pos = tile_id_to_pos(tile_id, self.palette_size, self.tile_size);
-        let selected_tile_pos = Self::local_coords_to_global(local_pos, palette_response);
+        let first_tile = self.tile_id_to_int_pos(tile_id);
+        let last_tile = first_tile + size - UVec2::ONE;
+        let local_rect = egui::Rect::from_min_max(
+            self.layout.int_tile_pos_to_pos(first_tile),
+            self.layout.int_tile_pos_to_pos(last_tile) + self.layout.tile_size,
+        );
+        let selected_tile_pos = Self::local_coords_to_global(local_rect.min, palette_response);
 
         painter.rect_stroke(
-            egui::Rect::from_min_size(selected_tile_pos, self.tile_size),
+            egui::Rect::from_min_size(selected_tile_pos, local_rect.size()),
             0.0,
             egui::Stroke::new(1.0, egui::Color32::RED),
         );
@@ -72,39 +116,72 @@ impl<'a> TilePalette<'a> {
 }
 
 impl<'a> egui::Widget for TilePalette<'a> {
-    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+    fn ui(mut self, ui: &mut egui::Ui) -> egui::Response {
         let palette_response = egui::ScrollArea::both()
             .always_show_scroll(true)
             .max_height(200.0)
             .max_width(200.0)
             .auto_shrink([false; 2])
-            .show(ui, |ui| ui.image(self.palette_texture, self.palette_size));
+            .show(ui, |ui| self.tileset.palette_ui(ui, &self.layout));
         let painter = ui.painter_at(palette_response.inner_rect);
 
+        if self.step != IVec2::ZERO {
+            self.apply_step();
+        }
+
         // Force the picked tile to zero if its out of range
-        if !self.tile_in_bounds() {
+        if !self.selection_in_bounds() {
             *self.selected_tile = 0;
+            *self.selection_size = UVec2::ONE;
         }
 
-        // The frame around the selected tile
-        self.paint_tile_picker(&palette_response, &painter, *self.selected_tile);
+        // The frame around the selected tiles
+        self.paint_tile_picker(
+            &palette_response,
+            &painter,
+            *self.selected_tile,
+            *self.selection_size,
+        );
 
         // The frame around the hovered tile
-        let hovered_tile_id = ui
+        let palette_size_in_tiles = self.layout.size_in_tiles();
+        let hovered_tile = ui
             .input(|x| x.pointer.hover_pos())
             .map(|p| Self::global_coords_to_local(p, &palette_response))
-            .map(|p| gridify_int(p, self.tile_size))
-            .and_then(|p| int_tile_pos_to_id(p, self.palette_size_in_tiles()))
+            .and_then(|p| self.layout.pos_to_int_tile_pos(p))
             .filter(|_| ui.rect_contains_pointer(palette_response.inner_rect));
+        let (pressed, down) = ui.input(|x| {
+            (
+                x.pointer.button_pressed(egui::PointerButton::Primary),
+                x.pointer.button_down(egui::PointerButton::Primary),
+            )
+        });
+        let drag_id = palette_response.id.with("drag_start");
+
+        if let Some(hovered_tile) = hovered_tile {
+            let hovered_tile_id = int_tile_pos_to_id(hovered_tile, palette_size_in_tiles).unwrap();
+            self.paint_tile_picker(&palette_response, &painter, hovered_tile_id, UVec2::ONE);
+
+            if pressed {
+                ui.data_mut(|x| x.insert_temp(drag_id, hovered_tile));
+            }
 
-        if let Some(hovered_tile_id) = hovered_tile_id {
-            self.paint_tile_picker(&palette_response, &painter, hovered_tile_id);
+            // Select everything between the tile where the drag started and the hovered one
+            if let Some(start) = ui.data_mut(|x| x.get_temp::<UVec2>(drag_id)) {
+                let min = start.min(hovered_tile);
+                let size = start.max(hovered_tile) - min + UVec2::ONE;
 
-            if ui.input(|x| x.pointer.button_clicked(egui::PointerButton::Primary)) {
-                *self.selected_tile = hovered_tile_id;
+                if self.block_fits(min, size) {
+                    *self.selected_tile = int_tile_pos_to_id(min, palette_size_in_tiles).unwrap();
+                    *self.selection_size = size;
+                }
             }
         }
 
+        if !down {
+            ui.data_mut(|x| x.remove::<UVec2>(drag_id));
+        }
+
         palette_response.inner
     }
 }
@@ -0,0 +1,85 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use bevy_editor_pls::egui;
+
+// The anchor buttons, top row first
+const ANCHOR_LABELS: [[&str; 3]; 3] = [["↖", "↑", "↗"], ["←", "•", "→"], ["↙", "↓", "↘"]];
+
+/// The settings for resizing a tilemap. The anchor is the point of the
+/// tilemap, that stays in place. Its coordinates go from 0 to 2,
+/// left to right and bottom to top.
+#[derive(Debug)]
+pub struct ResizePanel {
+    size: TilemapSize,
+    anchor: UVec2,
+}
+
+impl ResizePanel {
+    pub fn new(size: TilemapSize) -> Self {
+        Self {
+            size,
+            anchor: UVec2::ZERO,
+        }
+    }
+
+    pub fn size(&self) -> TilemapSize {
+        self.size
+    }
+
+    /// How much the tiles need to be moved by, when resizing from `old_size`
+    pub fn offset(&self, old_size: TilemapSize) -> IVec2 {
+        let axis_offset = |anchor, old, new| {
+            let diff = new as i32 - old as i32;
+
+            match anchor {
+                0 => 0,
+                1 => diff / 2,
+                _ => diff,
+            }
+        };
+
+        IVec2::new(
+            axis_offset(self.anchor.x, old_size.x, self.size.x),
+            axis_offset(self.anchor.y, old_size.y, self.size.y),
+        )
+    }
+
+    /// Returns `true` when the user asks for the resize. A locked
+    /// tilemap can't be resized.
+    pub fn ui(&mut self, ui: &mut egui::Ui, current_size: TilemapSize, locked: bool) -> bool {
+        ui.label(format!(
+            "Current size: {}x{}",
+            current_size.x, current_size.y
+        ));
+
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::DragValue::new(&mut self.size.x)
+                    .clamp_range(1..=u16::MAX as u32)
+                    .prefix("Width: "),
+            );
+            ui.add(
+                egui::DragValue::new(&mut self.size.y)
+                    .clamp_range(1..=u16::MAX as u32)
+                    .prefix("Height: "),
+            );
+        });
+
+        ui.label("Anchor");
+        egui::Grid::new("resize_anchor").show(ui, |ui| {
+            for (row, labels) in ANCHOR_LABELS.iter().enumerate() {
+                for (column, label) in labels.iter().enumerate() {
+                    let anchor = UVec2::new(column as u32, 2 - row as u32);
+
+                    ui.selectable_value(&mut self.anchor, anchor, *label);
+                }
+                ui.end_row();
+            }
+        });
+
+        let enabled = !locked && self.size != current_size;
+
+        ui.add_enabled(enabled, egui::Button::new("Resize"))
+            .clicked()
+    }
+}
@@ -0,0 +1,141 @@
+use bevy_ecs_tilemap::prelude::*;
+
+// All the cells of the line between two cells, both ends included
+fn bresenham_line(from: TilePos, to: TilePos) -> Vec<TilePos> {
+    let (mut x, mut y) = (from.x as i64, from.y as i64);
+    let (to_x, to_y) = (to.x as i64, to.y as i64);
+    let dx = (to_x - x).abs();
+    let dy = -(to_y - y).abs();
+    let step_x = (to_x - x).signum();
+    let step_y = (to_y - y).signum();
+    let mut err = dx + dy;
+    let mut cells = Vec::new();
+
+    loop {
+        cells.push(TilePos {
+            x: x as u32,
+            y: y as u32,
+        });
+
+        if x == to_x && y == to_y {
+            break;
+        }
+
+        let err2 = 2 * err;
+        if err2 >= dy {
+            err += dy;
+            x += step_x;
+        }
+        if err2 <= dx {
+            err += dx;
+            y += step_y;
+        }
+    }
+
+    cells
+}
+
+/// Tracks the cells swept by the pointer while the primary button is held.
+/// A stroke begins only with a press over the tilemap and ends once the
+/// button gets released or the pointer leaves the tilemap. The cells
+/// skipped between two frames get filled in, so fast drags leave no gaps.
+#[derive(Debug, Default)]
+pub struct BrushStroke {
+    last: Option<TilePos>,
+    cells: Vec<TilePos>,
+}
+
+impl BrushStroke {
+    /// Advances the stroke by a frame. `hovered_tile` is `None`
+    /// when the pointer isn't over the tilemap.
+    pub fn update(&mut self, hovered_tile: Option<TilePos>, pressed: bool, down: bool) {
+        self.cells.clear();
+
+        match (hovered_tile, self.last) {
+            (Some(pos), _) if pressed => {
+                self.cells.push(pos);
+                self.last = Some(pos);
+            }
+            (Some(pos), Some(last)) if down => {
+                self.cells
+                    .extend(bresenham_line(last, pos).into_iter().skip(1));
+                self.last = Some(pos);
+            }
+            _ => self.last = None,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.last.is_some()
+    }
+
+    /// The cells the stroke has entered this frame
+    pub fn cells(&self) -> &[TilePos] {
+        &self.cells
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(from: (u32, u32), to: (u32, u32)) -> Vec<(u32, u32)> {
+        let from = TilePos {
+            x: from.0,
+            y: from.1,
+        };
+        let to = TilePos { x: to.0, y: to.1 };
+
+        bresenham_line(from, to)
+            .into_iter()
+            .map(|pos| (pos.x, pos.y))
+            .collect()
+    }
+
+    #[test]
+    fn single_cell() {
+        assert_eq!(line((3, 4), (3, 4)), [(3, 4)]);
+    }
+
+    #[test]
+    fn steep_line() {
+        assert_eq!(
+            line((0, 0), (1, 4)),
+            [(0, 0), (0, 1), (1, 2), (1, 3), (1, 4)]
+        );
+    }
+
+    #[test]
+    fn backwards_line() {
+        let mut forwards = line((1, 2), (6, 4));
+        let backwards = line((6, 4), (1, 2));
+
+        assert_eq!(backwards.first(), Some(&(6, 4)));
+        assert_eq!(backwards.last(), Some(&(1, 2)));
+        assert_eq!(backwards.len(), 6);
+
+        // Every step moves to a neighbouring cell
+        forwards.reverse();
+        for cells in [forwards, backwards] {
+            for pair in cells.windows(2) {
+                assert!(pair[0].0.abs_diff(pair[1].0) <= 1);
+                assert!(pair[0].1.abs_diff(pair[1].1) <= 1);
+            }
+        }
+    }
+
+    #[test]
+    fn stroke_fills_gaps() {
+        let mut stroke = BrushStroke::default();
+
+        stroke.update(Some(TilePos { x: 0, y: 0 }), true, true);
+        assert_eq!(stroke.cells(), [TilePos { x: 0, y: 0 }]);
+
+        stroke.update(Some(TilePos { x: 3, y: 0 }), false, true);
+        assert_eq!(stroke.cells().len(), 3);
+
+        stroke.update(None, false, true);
+        assert!(!stroke.is_active());
+        assert!(stroke.cells().is_empty());
+    }
+}
@@ -0,0 +1,205 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use bevy_editor_pls::egui;
+use bevy_egui::EguiUserTextures;
+
+use crate::coord_utils::{bevy_to_egui, AtlasLayout};
+use crate::queries::TilemapQueryReadOnlyItem;
+use crate::EditorError;
+
+// How many images of a vector tileset the palette shows in one row
+const PALETTE_COLUMNS: u32 = 8;
+
+fn image_size(image: &Handle<Image>, world: &World) -> Result<Vec2, EditorError> {
+    world
+        .resource::<Assets<Image>>()
+        .get(image)
+        .map(|x| x.size())
+        .ok_or(EditorError::InvalidImageHandle {
+            handle: image.clone_weak(),
+        })
+}
+
+// egui can't show array textures, so every layer
+// gets copied into an image of its own
+fn split_layers(image: &Handle<Image>, world: &World) -> Result<Vec<Image>, EditorError> {
+    let image =
+        world
+            .resource::<Assets<Image>>()
+            .get(image)
+            .ok_or(EditorError::InvalidImageHandle {
+                handle: image.clone_weak(),
+            })?;
+    let mut texture_descriptor = image.texture_descriptor.clone();
+    let layer_count = texture_descriptor.size.depth_or_array_layers.max(1);
+    let layer_len = image.data.len() / layer_count as usize;
+
+    texture_descriptor.size.depth_or_array_layers = 1;
+
+    Ok(image
+        .data
+        .chunks_exact(layer_len.max(1))
+        .map(|data| Image {
+            data: data.to_vec(),
+            texture_descriptor: texture_descriptor.clone(),
+            sampler_descriptor: image.sampler_descriptor.clone(),
+            texture_view_descriptor: None,
+        })
+        .collect())
+}
+
+/// The textures of the edited tilemap, registered with egui.
+pub enum Tileset {
+    /// A single atlas image
+    Atlas {
+        image: Handle<Image>,
+        texture: egui::TextureId,
+    },
+    /// An image per tile. Texture containers get split into these too.
+    Images(Vec<(Handle<Image>, egui::TextureId)>),
+}
+
+impl Tileset {
+    pub fn new(texture: &TilemapTexture, world: &mut World) -> Result<Self, EditorError> {
+        let images = match texture {
+            TilemapTexture::Single(x) => {
+                return Ok(Self::Atlas {
+                    image: x.clone(),
+                    texture: world
+                        .resource_mut::<EguiUserTextures>()
+                        .add_image(x.clone()),
+                })
+            }
+            TilemapTexture::Vector(x) => x.clone(),
+            TilemapTexture::TextureContainer(x) => {
+                let layers = split_layers(x, world)?;
+                let mut assets = world.resource_mut::<Assets<Image>>();
+
+                layers.into_iter().map(|layer| assets.add(layer)).collect()
+            }
+        };
+        let mut egui_textures = world.resource_mut::<EguiUserTextures>();
+
+        Ok(Self::Images(
+            images
+                .into_iter()
+                .map(|image| (image.clone(), egui_textures.add_image(image)))
+                .collect(),
+        ))
+    }
+
+    pub fn cleanup(self, world: &mut World) {
+        let mut egui_textures = world.resource_mut::<EguiUserTextures>();
+
+        let images = match self {
+            Self::Atlas { image, .. } => vec![image],
+            Self::Images(x) => x.into_iter().map(|(image, _)| image).collect(),
+        };
+
+        for image in images {
+            egui_textures.remove_image(&image);
+        }
+    }
+
+    /// Where the tiles are on the picture the palette shows. The separate
+    /// images get laid out into rows, while atlases are shown as they are.
+    pub fn layout(
+        &self,
+        tilemap: &TilemapQueryReadOnlyItem,
+        world: &World,
+    ) -> Result<AtlasLayout, EditorError> {
+        let tile_size = bevy_to_egui(tilemap.tile_size.into());
+
+        match self {
+            Self::Atlas { image, .. } => Ok(AtlasLayout {
+                atlas_size: bevy_to_egui(image_size(image, world)?),
+                tile_size,
+                spacing: tilemap
+                    .spacing
+                    .map(|x| bevy_to_egui((*x).into()))
+                    .unwrap_or_default(),
+                margin: tilemap
+                    .margin
+                    .map(|x| egui::vec2(x.x, x.y))
+                    .unwrap_or_default(),
+            }),
+            Self::Images(x) => {
+                let count = x.len() as u32;
+                let columns = count.min(PALETTE_COLUMNS);
+                let rows = count.div_ceil(PALETTE_COLUMNS);
+
+                Ok(AtlasLayout {
+                    atlas_size: egui::vec2(columns as f32 * tile_size.x, rows as f32 * tile_size.y),
+                    tile_size,
+                    spacing: egui::Vec2::ZERO,
+                    margin: egui::Vec2::ZERO,
+                })
+            }
+        }
+    }
+
+    /// How many tiles there are, if the tileset consists of separate images.
+    /// The last row of the palette might have fewer of them than the others.
+    pub fn tile_count(&self) -> Option<u32> {
+        match self {
+            Self::Atlas { .. } => None,
+            Self::Images(x) => Some(x.len() as u32),
+        }
+    }
+
+    /// The texture to paint a tile with, and the UV rect of the tile on it
+    pub fn tile_info(
+        &self,
+        id: u32,
+        layout: &AtlasLayout,
+    ) -> Result<(egui::TextureId, egui::Rect), EditorError> {
+        match self {
+            Self::Atlas { texture, .. } => {
+                let atlas_size = layout.atlas_size;
+                let pos = layout.tile_id_to_pos(id);
+                let uv = egui::Rect::from_min_size(pos, layout.tile_size);
+
+                Ok((
+                    *texture,
+                    egui::Rect {
+                        min: egui::pos2(uv.min.x / atlas_size.x, uv.min.y / atlas_size.y),
+                        max: egui::pos2(uv.max.x / atlas_size.x, uv.max.y / atlas_size.y),
+                    },
+                ))
+            }
+            Self::Images(x) => x
+                .get(id as usize)
+                .map(|(_, texture)| {
+                    let uv = egui::Rect::from_min_max(egui::Pos2::ZERO, egui::pos2(1.0, 1.0));
+
+                    (*texture, uv)
+                })
+                .ok_or(EditorError::BadTileTextureIndex(id)),
+        }
+    }
+
+    /// Paints the picture the palette shows
+    pub fn palette_ui(&self, ui: &mut egui::Ui, layout: &AtlasLayout) -> egui::Response {
+        match self {
+            Self::Atlas { texture, .. } => ui.image(*texture, layout.atlas_size),
+            Self::Images(x) => {
+                let (rect, response) =
+                    ui.allocate_exact_size(layout.atlas_size, egui::Sense::hover());
+                let uv = egui::Rect::from_min_max(egui::Pos2::ZERO, egui::pos2(1.0, 1.0));
+
+                x.iter().enumerate().for_each(|(id, (_, texture))| {
+                    let pos = layout.tile_id_to_pos(id as u32);
+
+                    ui.painter().image(
+                        *texture,
+                        egui::Rect::from_min_size(rect.min + pos.to_vec2(), layout.tile_size),
+                        uv,
+                        egui::Color32::WHITE,
+                    );
+                });
+
+                response
+            }
+        }
+    }
+}
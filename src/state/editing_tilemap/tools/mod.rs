@@ -1,22 +1,83 @@
+mod tile_bucket;
 mod tile_eraser;
 mod tile_painter;
 mod tile_picker;
+mod tile_rect_fill;
+mod tile_selector;
 mod tile_whois;
 
-use crate::coord_utils::tile_id_to_pos;
+use crate::coord_utils::AtlasLayout;
+use std::sync::Arc;
+
 use crate::queries::{TilePropertyQuery, TilemapPoints, TilemapQuery};
 use bevy::prelude::*;
+use bevy_ecs_tilemap::helpers::hex_grid::neighbors::HexNeighbors;
+use bevy_ecs_tilemap::helpers::square_grid::neighbors::Neighbors;
 use bevy_editor_pls::egui::{self, Painter};
 
+use super::clipboard::TileStamp;
+use super::history::{EditHistory, TileSnapshot};
+use super::stroke::BrushStroke;
+use super::tileset::Tileset;
 use super::*;
 
+pub use tile_bucket::TileBucket;
 pub use tile_eraser::TileEraser;
 pub use tile_painter::TilePainter;
 pub use tile_picker::TilePicker;
+pub use tile_rect_fill::TileRectFill;
+pub use tile_selector::TileSelector;
 pub use tile_whois::TileWhoIs;
 
 pub type Result<T> = core::result::Result<T, EditorError>;
 
+// The largest size the brush and the eraser can be set to
+const MAX_TOOL_SIZE: u32 = 16;
+
+fn size_ui(ui: &mut egui::Ui, size: &mut u32) {
+    ui.horizontal(|ui| {
+        ui.label("Size");
+        ui.add(egui::Slider::new(size, 1..=MAX_TOOL_SIZE));
+    });
+}
+
+// All the tiles of the rectangle spanned by two corners, row by row
+pub(super) fn rect_tiles(
+    a: TilePos,
+    b: TilePos,
+    outline_only: bool,
+) -> impl Iterator<Item = TilePos> {
+    let min = UVec2::new(a.x.min(b.x), a.y.min(b.y));
+    let max = UVec2::new(a.x.max(b.x), a.y.max(b.y));
+
+    (min.y..=max.y)
+        .flat_map(move |y| (min.x..=max.x).map(move |x| TilePos { x, y }))
+        .filter(move |pos| {
+            !outline_only || pos.x == min.x || pos.x == max.x || pos.y == min.y || pos.y == max.y
+        })
+}
+
+// Where a cell of a painted block comes from in the atlas, relative to the
+// block's top-left corner there. `size` is the size of the painted block.
+// The flips get undone the same way the tile textures get them.
+fn block_atlas_offset(offset: UVec2, size: UVec2, flip: TileFlip) -> UVec2 {
+    // The atlas' Y goes down
+    let mut atlas_pos = UVec2::new(offset.x, size.y - 1 - offset.y);
+
+    if flip.x {
+        atlas_pos.x = size.x - 1 - atlas_pos.x;
+    }
+    if flip.y {
+        atlas_pos.y = size.y - 1 - atlas_pos.y;
+    }
+    if flip.d {
+        atlas_pos = UVec2::new(atlas_pos.y, atlas_pos.x);
+    }
+
+    atlas_pos
+}
+
+/// The look of a tile: everything the brush carries besides the tile data
 #[derive(Clone, Copy, Debug)]
 pub struct TileProperties {
     pub color: TileColor,
@@ -34,15 +95,23 @@ impl Default for TileProperties {
     }
 }
 
+/// What a [`Tool`] gets to work with while the user hovers over the tilemap.
+/// The tile edits done through it end up in the undo history.
 pub struct ToolContext<'w, 's> {
     world: &'w mut World,
     points: TilemapPoints,
     tilemap_entity: Entity,
-    tilemap_texture_egui: egui::TextureId,
+    tileset: &'s Tileset,
     tile_query: &'s mut QueryState<TilePropertyQuery, ()>,
     tilemap_query: &'s mut QueryState<TilemapQuery, ()>,
     tile_data: &'s mut HashMap<u32, TileData>,
     brush_state: &'s mut TileProperties,
+    brush_size: &'s mut UVec2,
+    history: &'s mut EditHistory,
+    clipboard: &'s mut Option<TileStamp>,
+    selection: &'s mut Option<(TilePos, TilePos)>,
+    keymap: &'s TilemapEditorKeymap,
+    stroke: &'s BrushStroke,
 }
 
 impl<'w, 's> ToolContext<'w, 's> {
@@ -51,24 +120,77 @@ impl<'w, 's> ToolContext<'w, 's> {
         world: &'w mut World,
         points: TilemapPoints,
         tilemap_entity: Entity,
-        tilemap_texture_egui: egui::TextureId,
+        tileset: &'s Tileset,
         tile_query: &'s mut QueryState<TilePropertyQuery, ()>,
         tilemap_query: &'s mut QueryState<TilemapQuery, ()>,
         tile_data: &'s mut HashMap<u32, TileData>,
         brush_state: &'s mut TileProperties,
+        brush_size: &'s mut UVec2,
+        history: &'s mut EditHistory,
+        clipboard: &'s mut Option<TileStamp>,
+        selection: &'s mut Option<(TilePos, TilePos)>,
+        keymap: &'s TilemapEditorKeymap,
+        stroke: &'s BrushStroke,
     ) -> Self {
         Self {
             world,
             points,
             tilemap_entity,
-            tilemap_texture_egui,
+            tileset,
             tile_query,
             tilemap_query,
             tile_data,
             brush_state,
+            brush_size,
+            history,
+            clipboard,
+            selection,
+            keymap,
+            stroke,
         }
     }
 
+    pub fn tilemap_entity(&self) -> Entity {
+        self.tilemap_entity
+    }
+
+    pub fn world(&self) -> &World {
+        self.world
+    }
+
+    /// The world, for the tools that need more than editing tiles.
+    /// Nothing changed through it gets recorded in the undo history.
+    pub fn world_mut(&mut self) -> &mut World {
+        self.world
+    }
+
+    /// The tile the user has picked from the palette
+    pub fn brush(&self) -> TileProperties {
+        *self.brush_state
+    }
+
+    /// Whether the primary button is being held down after
+    /// having been pressed over the tilemap
+    pub fn stroke_active(&self) -> bool {
+        self.stroke.is_active()
+    }
+
+    /// The cells the pointer has swept through this frame while
+    /// the stroke is active. Empty when there's no stroke.
+    pub fn stroke_cells(&self) -> Vec<TilePos> {
+        self.stroke.cells().to_vec()
+    }
+
+    /// Whether the shortcut of the action has been pressed this frame
+    pub fn pressed(&self, action: EditorAction, ui: &egui::Ui) -> bool {
+        self.keymap.pressed(action, ui.ctx())
+    }
+
+    /// Whether the modifier of the action is being held down
+    pub fn held(&self, action: EditorHoldAction, ui: &egui::Ui) -> bool {
+        self.keymap.held(action, ui.ctx())
+    }
+
     pub fn get_tile(&self, pos: TilePos) -> Result<Option<Entity>> {
         let storage = self
             .tilemap_query
@@ -82,8 +204,51 @@ impl<'w, 's> ToolContext<'w, 's> {
         Ok(storage.get(&pos))
     }
 
+    pub fn tilemap_size(&self) -> Result<TilemapSize> {
+        let storage = self
+            .tilemap_query
+            .get_manual(self.world, self.tilemap_entity)
+            .map_err(|query_error| EditorError::BadTilemapEntity {
+                tilemap_entity: self.tilemap_entity,
+                query_error,
+            })?
+            .storage;
+
+        Ok(storage.size)
+    }
+
+    /// The positions of the tiles next to `pos`, that are within the tilemap.
+    /// Hexagonal tiles don't have diagonal neighbors, so `include_diagonals`
+    /// doesn't matter for them.
+    pub fn tile_neighbors(&self, pos: TilePos, include_diagonals: bool) -> Result<Vec<TilePos>> {
+        let tilemap = self
+            .tilemap_query
+            .get_manual(self.world, self.tilemap_entity)
+            .map_err(|query_error| EditorError::BadTilemapEntity {
+                tilemap_entity: self.tilemap_entity,
+                query_error,
+            })?;
+        let size = tilemap.storage.size;
+
+        let neighbors = match tilemap.ty {
+            TilemapType::Hexagon(hex_coord_sys) => {
+                HexNeighbors::get_neighboring_positions(&pos, &size, hex_coord_sys)
+                    .iter()
+                    .copied()
+                    .collect()
+            }
+            _ => Neighbors::get_square_neighboring_positions(&pos, &size, include_diagonals)
+                .iter()
+                .copied()
+                .collect(),
+        };
+
+        Ok(neighbors)
+    }
+
     pub fn despawn_tile(&mut self, pos: TilePos) -> Result<()> {
         let Some(tile_entity) = self.get_tile(pos)? else { return Ok(()); };
+        let before = self.snapshot_tile(pos)?;
 
         self.world.entity_mut(tile_entity).despawn_recursive();
         self.tilemap_query
@@ -94,11 +259,24 @@ impl<'w, 's> ToolContext<'w, 's> {
             })?
             .storage
             .remove(&pos);
+        self.history.record(self.tilemap_entity, pos, before, None);
 
         Ok(())
     }
 
     pub fn set_tile_properties(&mut self, tile_pos: TilePos, props: TileProperties) -> Result<()> {
+        let before = self.snapshot_tile(tile_pos)?;
+        self.apply_tile_properties(tile_pos, props)?;
+
+        let after = self.snapshot_tile(tile_pos)?;
+        self.history
+            .record(self.tilemap_entity, tile_pos, before, after);
+
+        Ok(())
+    }
+
+    // Same as `set_tile_properties`, but leaves the history to the caller
+    fn apply_tile_properties(&mut self, tile_pos: TilePos, props: TileProperties) -> Result<()> {
         let tile_entity = match self.get_tile(tile_pos)? {
             Some(x) => x,
             None => {
@@ -154,6 +332,67 @@ impl<'w, 's> ToolContext<'w, 's> {
         Ok(())
     }
 
+    /// Puts a previously captured tile at `tile_pos`, including the values
+    /// of its tile data components.
+    pub(crate) fn place_tile(&mut self, tile_pos: TilePos, snapshot: &TileSnapshot) -> Result<()> {
+        let before = self.snapshot_tile(tile_pos)?;
+        self.apply_tile_properties(tile_pos, snapshot.props)?;
+
+        if let Some(tile_entity) = self.get_tile(tile_pos)? {
+            snapshot.insert_components(&mut self.world.entity_mut(tile_entity));
+        }
+
+        let after = self.snapshot_tile(tile_pos)?;
+        self.history
+            .record(self.tilemap_entity, tile_pos, before, after);
+
+        Ok(())
+    }
+
+    /// Captures the rectangle spanned by two corners.
+    pub(crate) fn copy_region(&self, a: TilePos, b: TilePos) -> Result<TileStamp> {
+        let size = UVec2::new(a.x.abs_diff(b.x) + 1, a.y.abs_diff(b.y) + 1);
+        let tiles = rect_tiles(a, b, false)
+            .map(|pos| self.snapshot_tile(pos))
+            .collect::<Result<_>>()?;
+
+        Ok(TileStamp::new(size, tiles))
+    }
+
+    pub(crate) fn history_revision(&self) -> u64 {
+        self.history.revision()
+    }
+
+    pub(crate) fn clipboard(&self) -> Option<&TileStamp> {
+        self.clipboard.as_ref()
+    }
+
+    pub(crate) fn set_clipboard(&mut self, stamp: TileStamp) {
+        *self.clipboard = Some(stamp);
+    }
+
+    /// The corners of the selected rectangle, if anything is selected
+    pub(crate) fn selection(&self) -> Option<(TilePos, TilePos)> {
+        *self.selection
+    }
+
+    pub(crate) fn set_selection(&mut self, selection: Option<(TilePos, TilePos)>) {
+        *self.selection = selection;
+    }
+
+    fn snapshot_tile(&self, tile_pos: TilePos) -> Result<Option<TileSnapshot>> {
+        let Some((tile_entity, props)) = self.get_tile_properties(tile_pos)? else {
+            return Ok(None)
+        };
+        let components = self
+            .tile_data
+            .get(&props.texture.0)
+            .map(|data| data.capture(self.world.entity(tile_entity)))
+            .unwrap_or_default();
+
+        Ok(Some(TileSnapshot { props, components }))
+    }
+
     pub fn get_tile_properties(
         &self,
         tile_pos: TilePos,
@@ -181,94 +420,112 @@ impl<'w, 's> ToolContext<'w, 's> {
         )))
     }
 
-    pub fn tile_rect(&self, pos: TilePos) -> egui::Rect {
-        let sample_rect = self.points.grid_sample_rect();
-        let sample_size = sample_rect.size();
-        let offset = egui::vec2(
-            pos.x as f32 * sample_size.x,
-            // Flip the Y axis, because the TilePos goes up
-            // BUT egui Y goes down
-            -(pos.y as f32) * sample_size.y,
-        );
+    /// The outline of the tile's cell on the screen
+    pub fn tile_outline(&self, pos: TilePos) -> Vec<egui::Pos2> {
+        self.points.tile_outline(pos)
+    }
 
-        sample_rect.translate(offset)
+    /// The outline of the rectangle of tiles spanned by two corners
+    pub fn region_outline(&self, a: TilePos, b: TilePos) -> Vec<egui::Pos2> {
+        self.points.region_outline(a, b)
     }
 
-    fn brush_mesh(&self, rect: egui::Rect, uv: egui::Rect) -> egui::Shape {
-        let [r, g, b, a] = self.brush_state.color.0.as_rgba_f32();
+    fn brush_mesh(
+        &self,
+        quad: [egui::Pos2; 4],
+        (texture, uv): (egui::TextureId, egui::Rect),
+        props: &TileProperties,
+    ) -> egui::Shape {
+        let [r, g, b, a] = props.color.0.as_rgba_f32();
         let color = egui::Color32::from_rgba_unmultiplied(
             (r * 255.0) as u8,
             (g * 255.0) as u8,
             (b * 255.0) as u8,
             (a * 255.0) as u8,
         );
-        let mut mesh = egui::Mesh::with_texture(self.tilemap_texture_egui);
+        let mut mesh = egui::Mesh::with_texture(texture);
+
+        // The quad's corners go clockwise from the top-left one.
+        // The flips get applied first, then the diagonal flip.
+        let corners = [
+            egui::vec2(0.0, 0.0),
+            egui::vec2(1.0, 0.0),
+            egui::vec2(1.0, 1.0),
+            egui::vec2(0.0, 1.0),
+        ];
 
         mesh.indices.extend([0, 1, 2, 0, 2, 3]);
-        mesh.vertices.extend([
-            egui::epaint::Vertex {
-                color,
-                pos: rect.left_top(),
-                uv: uv.left_top(),
-            },
-            egui::epaint::Vertex {
-                color,
-                pos: rect.right_top(),
-                uv: uv.right_top(),
-            },
-            egui::epaint::Vertex {
-                color,
-                pos: rect.right_bottom(),
-                uv: uv.right_bottom(),
-            },
-            egui::epaint::Vertex {
-                color,
-                pos: rect.left_bottom(),
-                uv: uv.left_bottom(),
-            },
-        ]);
+        mesh.vertices
+            .extend(quad.into_iter().zip(corners).map(|(pos, mut corner)| {
+                if props.flip.x {
+                    corner.x = 1.0 - corner.x;
+                }
+                if props.flip.y {
+                    corner.y = 1.0 - corner.y;
+                }
+                if props.flip.d {
+                    corner = egui::vec2(corner.y, corner.x);
+                }
+
+                egui::epaint::Vertex {
+                    color,
+                    pos,
+                    uv: uv.min + corner * uv.size(),
+                }
+            }));
 
-        let trans = rect.center().to_vec2();
+        egui::Shape::mesh(mesh)
+    }
 
-        // Undo translate
-        mesh.translate(-trans);
+    pub fn paint_tile(&self, pos: TilePos, painter: &Painter) -> Result<()> {
+        self.paint_tile_with(pos, self.brush_state, painter)
+    }
 
-        if self.brush_state.flip.d {
-            mesh.rotate(
-                egui::emath::Rot2::from_angle(std::f32::consts::FRAC_PI_2),
-                egui::Pos2::ZERO,
-            )
-        }
+    /// Like [`ToolContext::paint_tile`], but paints the given tile instead of the brush.
+    pub fn paint_tile_with(
+        &self,
+        pos: TilePos,
+        props: &TileProperties,
+        painter: &Painter,
+    ) -> Result<()> {
+        painter.add(self.brush_mesh(
+            self.points.tile_quad(pos),
+            self.tile_info(props.texture.0)?,
+            props,
+        ));
 
-        // Combine x and y flips into negative scaling
-        let mut scale = egui::vec2(
-            1.0 - 2.0 * self.brush_state.flip.x as u8 as f32,
-            1.0 - 2.0 * self.brush_state.flip.y as u8 as f32,
-        );
+        Ok(())
+    }
 
-        // multiply that scale by d
-        if self.brush_state.flip.d {
-            scale.x *= -1.0;
-        }
+    /// The tiles the brush consists of, along with their offsets from its
+    /// bottom-left corner. The flip of the brush applies to the whole block
+    /// of tiles, not just to each tile separately.
+    pub fn brush_tiles(&self) -> Result<Vec<(UVec2, TileProperties)>> {
+        let brush = *self.brush_state;
+        let columns = self.atlas_layout()?.size_in_tiles().x.max(1);
+        let origin = UVec2::new(brush.texture.0 % columns, brush.texture.0 / columns);
+        let size = match brush.flip.d {
+            true => UVec2::new(self.brush_size.y, self.brush_size.x),
+            false => *self.brush_size,
+        };
 
-        // Apply the scale
-        mesh.vertices
-            .iter_mut()
-            .for_each(|v| v.pos = egui::pos2(scale.x * v.pos.x, scale.y * v.pos.y));
+        let tiles = (0..size.y)
+            .flat_map(|y| (0..size.x).map(move |x| UVec2::new(x, y)))
+            .map(|offset| {
+                let atlas_pos = origin + block_atlas_offset(offset, size, brush.flip);
+                let props = TileProperties {
+                    texture: TileTextureIndex(atlas_pos.x + atlas_pos.y * columns),
+                    ..brush
+                };
 
-        // Reapply translate
-        mesh.translate(trans);
+                (offset, props)
+            })
+            .collect();
 
-        egui::Shape::mesh(mesh)
+        Ok(tiles)
     }
 
-    pub fn paint_tile(&self, ui_rect: egui::Rect, painter: &Painter) -> Result<()> {
-        painter.add(self.brush_mesh(ui_rect, self.tile_info(self.brush_state.texture.0)?));
-
-        Ok(())
-    }
-
-    fn tile_info(&self, id: u32) -> Result<egui::Rect> {
+    fn atlas_layout(&self) -> Result<AtlasLayout> {
         let tilemap = self
             .tilemap_query
             .get_manual(self.world, self.tilemap_entity)
@@ -277,30 +534,11 @@ impl<'w, 's> ToolContext<'w, 's> {
                 query_error,
             })?;
 
-        match &tilemap.texture {
-            TilemapTexture::Single(x) => {
-                let tile_size = bevy_to_egui(tilemap.tile_size.into());
-                let atlas_size = self
-                    .world
-                    .resource::<Assets<Image>>()
-                    .get(x)
-                    .ok_or(EditorError::InvalidImageHandle {
-                        handle: x.clone_weak(),
-                    })?
-                    .size();
-                let pos = tile_id_to_pos(id, bevy_to_egui(atlas_size), tile_size);
-                let uv = egui::Rect::from_min_size(pos, tile_size);
-
-                Ok(egui::Rect {
-                    min: egui::pos2(uv.min.x / atlas_size.x, uv.min.y / atlas_size.y),
-                    max: egui::pos2(uv.max.x / atlas_size.x, uv.max.y / atlas_size.y),
-                })
-            }
-            TilemapTexture::Vector(_) => Err(EditorError::UnsupportedTilemapTextureType("Vector")),
-            TilemapTexture::TextureContainer(_) => Err(EditorError::UnsupportedTilemapTextureType(
-                "TextureContainer",
-            )),
-        }
+        self.tileset.layout(&tilemap, self.world)
+    }
+
+    fn tile_info(&self, id: u32) -> Result<(egui::TextureId, egui::Rect)> {
+        self.tileset.tile_info(id, &self.atlas_layout()?)
     }
 
     pub fn copy_tile_properties(&mut self, tile_pos: TilePos) -> Result<()> {
@@ -308,6 +546,7 @@ impl<'w, 's> ToolContext<'w, 's> {
         let tile_entity = self.world.entity(tile_entity);
 
         *self.brush_state = props;
+        *self.brush_size = UVec2::ONE;
         let Some(tile_data) = self.tile_data.get_mut(&props.texture.0) else { return Ok(()) };
 
         tile_data.apply(tile_entity);
@@ -316,9 +555,26 @@ impl<'w, 's> ToolContext<'w, 's> {
     }
 }
 
+/// Something the user can do to the tilemap with the pointer. Custom tools
+/// get registered through [`crate::AddTilemapEditorTool`]. The editor keeps a single instance of
+/// each tool, so its settings stay the same when switching tilemaps.
 pub trait Tool: std::fmt::Debug + Send + Sync {
     fn name(&self) -> &'static str;
 
+    /// A short piece of text, like an emoji, shown before the name in the tool row
+    fn icon(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Draws the tool's options in the editor's side panel while the tool is selected
+    fn settings_ui(&mut self, _ui: &mut egui::Ui) {}
+
+    /// Called when the editor switches to another tilemap. The tool should drop
+    /// whatever refers to the tiles of the previous one, but keep its settings.
+    fn tilemap_changed(&mut self) {}
+
+    /// Runs every frame while the tool is selected and the pointer is over
+    /// the tilemap. Whatever gets painted should go through `painter`.
     fn viewport_ui(
         &mut self,
         ctx: &mut ToolContext,
@@ -327,3 +583,78 @@ pub trait Tool: std::fmt::Debug + Send + Sync {
         painter: &Painter,
     ) -> Result<()>;
 }
+
+type ToolFactory = Arc<dyn Fn() -> Box<dyn Tool> + Send + Sync>;
+
+/// The tools shown in the editor, in the order of the tool row
+#[derive(Clone, Resource)]
+pub(crate) struct EditorTools(Vec<ToolFactory>);
+
+impl EditorTools {
+    pub fn add<T: Tool + Default + 'static>(&mut self) {
+        self.0.push(Arc::new(|| Box::new(T::default())));
+    }
+
+    pub fn create(&self) -> Vec<Box<dyn Tool>> {
+        self.0.iter().map(|factory| factory()).collect()
+    }
+}
+
+impl Default for EditorTools {
+    fn default() -> Self {
+        let mut tools = Self(Vec::new());
+
+        tools.add::<TilePainter>();
+        tools.add::<TileRectFill>();
+        tools.add::<TileBucket>();
+        tools.add::<TileSelector>();
+        tools.add::<TileEraser>();
+        tools.add::<TilePicker>();
+        tools.add::<TileWhoIs>();
+
+        tools
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn block_keeps_the_atlas_layout() {
+        let cell = |x, y| block_atlas_offset(UVec2::new(x, y), UVec2::new(2, 2), default());
+
+        // The bottom row of the painted block is the lower row of the atlas block
+        assert_eq!(cell(0, 0), UVec2::new(0, 1));
+        assert_eq!(cell(1, 1), UVec2::new(1, 0));
+    }
+
+    #[test]
+    fn flipped_block_uses_every_tile_once() {
+        let atlas_size = UVec2::new(3, 2);
+
+        for bits in 0..8 {
+            let flip = TileFlip {
+                x: bits & 1 != 0,
+                y: bits & 2 != 0,
+                d: bits & 4 != 0,
+            };
+            let size = match flip.d {
+                true => UVec2::new(atlas_size.y, atlas_size.x),
+                false => atlas_size,
+            };
+
+            let mut cells: Vec<_> = (0..size.y)
+                .flat_map(|y| (0..size.x).map(move |x| UVec2::new(x, y)))
+                .map(|offset| block_atlas_offset(offset, size, flip))
+                .map(|pos| (pos.y, pos.x))
+                .collect();
+            cells.sort();
+
+            let expected: Vec<_> = (0..atlas_size.y)
+                .flat_map(|y| (0..atlas_size.x).map(move |x| (y, x)))
+                .collect();
+            assert_eq!(cells, expected, "{flip:?}");
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::{HashSet, VecDeque};
+
+use super::*;
+
+use bevy_ecs_tilemap::prelude::*;
+use bevy_editor_pls::egui;
+
+// Bigger regions only get previewed in part, so that hovering
+// over a large empty tilemap stays cheap
+const MAX_PREVIEW_LEN: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Connectivity {
+    Four,
+    Eight,
+}
+
+/// Replaces a contiguous region of similar tiles (or of empty cells)
+/// with the current brush.
+#[derive(Debug)]
+pub struct TileBucket {
+    connectivity: Connectivity,
+    match_flip: bool,
+    match_color: bool,
+    // The region under the cursor, so it doesn't get recomputed every frame
+    preview: Option<Preview>,
+}
+
+#[derive(Debug)]
+struct Preview {
+    pos: TilePos,
+    // The history revision the region was found at
+    revision: u64,
+    region: Vec<TilePos>,
+    // Whether the region got cut short at `MAX_PREVIEW_LEN`
+    partial: bool,
+}
+
+impl Default for TileBucket {
+    fn default() -> Self {
+        Self {
+            connectivity: Connectivity::Four,
+            match_flip: false,
+            match_color: false,
+            preview: None,
+        }
+    }
+}
+
+impl TileBucket {
+    fn is_similar(&self, a: Option<TileProperties>, b: Option<TileProperties>) -> bool {
+        match (a, b) {
+            (None, None) => true,
+            (Some(a), Some(b)) => {
+                a.texture.0 == b.texture.0
+                    && (!self.match_flip
+                        || (a.flip.x == b.flip.x && a.flip.y == b.flip.y && a.flip.d == b.flip.d))
+                    && (!self.match_color || a.color.0 == b.color.0)
+            }
+            _ => false,
+        }
+    }
+
+    // Returns the region and whether it got cut short at `limit` tiles
+    fn region(
+        &self,
+        ctx: &ToolContext,
+        start: TilePos,
+        limit: usize,
+    ) -> Result<(Vec<TilePos>, bool)> {
+        let include_diagonals = self.connectivity == Connectivity::Eight;
+        let props_at =
+            |pos| -> Result<_> { Ok(ctx.get_tile_properties(pos)?.map(|(_, props)| props)) };
+        let target = props_at(start)?;
+        let mut visited = HashSet::from([start]);
+        let mut queue = VecDeque::from([start]);
+        let mut region = Vec::new();
+
+        while let Some(pos) = queue.pop_front() {
+            if region.len() >= limit {
+                return Ok((region, true));
+            }
+            region.push(pos);
+
+            for neighbor in ctx.tile_neighbors(pos, include_diagonals)? {
+                if visited.insert(neighbor) && self.is_similar(target, props_at(neighbor)?) {
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+
+        Ok((region, false))
+    }
+}
+
+impl Tool for TileBucket {
+    fn name(&self) -> &'static str {
+        "Bucket"
+    }
+
+    fn settings_ui(&mut self, ui: &mut egui::Ui) {
+        let old = (self.connectivity, self.match_flip, self.match_color);
+
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.connectivity, Connectivity::Four, "4-way");
+            ui.selectable_value(&mut self.connectivity, Connectivity::Eight, "8-way");
+        });
+        ui.checkbox(&mut self.match_flip, "Match flip");
+        ui.checkbox(&mut self.match_color, "Match color");
+
+        if old != (self.connectivity, self.match_flip, self.match_color) {
+            self.preview = None;
+        }
+    }
+
+    fn tilemap_changed(&mut self) {
+        self.preview = None;
+    }
+
+    fn viewport_ui(
+        &mut self,
+        ctx: &mut ToolContext,
+        hovered_tile: TilePos,
+        ui: &mut egui::Ui,
+        painter: &Painter,
+    ) -> Result<()> {
+        let revision = ctx.history_revision();
+
+        if !matches!(&self.preview, Some(x) if x.pos == hovered_tile && x.revision == revision) {
+            let (region, partial) = self.region(ctx, hovered_tile, MAX_PREVIEW_LEN)?;
+
+            self.preview = Some(Preview {
+                pos: hovered_tile,
+                revision,
+                region,
+                partial,
+            });
+        }
+        let Some(preview) = &self.preview else { return Ok(()); };
+
+        for pos in &preview.region {
+            ctx.paint_tile(*pos, painter)?;
+        }
+        painter.add(egui::Shape::closed_line(
+            ctx.tile_outline(hovered_tile),
+            egui::Stroke::new(1.0, egui::Color32::RED),
+        ));
+
+        // The preview follows every edit made through the editor, so only
+        // a partial one has to be found anew for the fill
+        if ui.input(|x| x.pointer.button_clicked(egui::PointerButton::Primary)) {
+            let Some(preview) = self.preview.take() else { return Ok(()); };
+            let region = match preview.partial {
+                true => self.region(ctx, hovered_tile, usize::MAX)?.0,
+                false => preview.region,
+            };
+
+            for pos in region {
+                ctx.set_tile_properties(pos, ctx.brush())?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -3,30 +3,88 @@ use super::*;
 use bevy_ecs_tilemap::prelude::*;
 use bevy_editor_pls::egui;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EraserMode {
+    AnyTile,
+    /// Only the tiles with the same texture as the brush
+    BrushTexture,
+}
+
+/// Erases a square of tiles, hanging off the cursor by its bottom-left corner
 #[derive(Debug)]
-pub struct TileEraser;
+pub struct TileEraser {
+    mode: EraserMode,
+    size: u32,
+}
+
+impl Default for TileEraser {
+    fn default() -> Self {
+        Self {
+            mode: EraserMode::AnyTile,
+            size: 1,
+        }
+    }
+}
+
+impl TileEraser {
+    fn square_at(&self, ctx: &ToolContext, cell: TilePos) -> Result<(TilePos, TilePos)> {
+        let size = ctx.tilemap_size()?;
+        let far_corner = TilePos {
+            x: (cell.x + self.size - 1).min(size.x.saturating_sub(1)),
+            y: (cell.y + self.size - 1).min(size.y.saturating_sub(1)),
+        };
+
+        Ok((cell, far_corner))
+    }
+}
 
 impl Tool for TileEraser {
     fn name(&self) -> &'static str {
         "Eraser"
     }
 
+    fn settings_ui(&mut self, ui: &mut egui::Ui) {
+        size_ui(ui, &mut self.size);
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.mode, EraserMode::AnyTile, "Any tile");
+            ui.selectable_value(
+                &mut self.mode,
+                EraserMode::BrushTexture,
+                "Brush texture only",
+            );
+        });
+    }
+
     fn viewport_ui(
         &mut self,
         ctx: &mut ToolContext,
         hovered_tile: TilePos,
-        ui: &mut egui::Ui,
+        _ui: &mut egui::Ui,
         painter: &Painter,
     ) -> Result<()> {
-        let display_rect = ctx.tile_rect(hovered_tile);
-        painter.rect_stroke(
-            display_rect,
-            0.0,
+        let (a, b) = self.square_at(ctx, hovered_tile)?;
+
+        painter.add(egui::Shape::closed_line(
+            ctx.region_outline(a, b),
             egui::Stroke::new(1.0, egui::Color32::RED),
-        );
+        ));
+
+        for cell in ctx.stroke_cells() {
+            let (a, b) = self.square_at(ctx, cell)?;
+
+            for pos in rect_tiles(a, b, false) {
+                let erase = match self.mode {
+                    EraserMode::AnyTile => true,
+                    EraserMode::BrushTexture => matches!(
+                        ctx.get_tile_properties(pos)?,
+                        Some((_, props)) if props.texture.0 == ctx.brush().texture.0
+                    ),
+                };
 
-        if ui.input(|x| x.pointer.button_down(egui::PointerButton::Primary)) {
-            ctx.despawn_tile(hovered_tile)?;
+                if erase {
+                    ctx.despawn_tile(pos)?;
+                }
+            }
         }
 
         Ok(())
@@ -3,31 +3,85 @@ use super::*;
 use bevy_ecs_tilemap::prelude::*;
 use bevy_editor_pls::egui;
 
+/// Paints with the brush. The brush can be repeated a few times
+/// along each axis to cover more ground with a single stroke.
 #[derive(Debug)]
-pub struct TilePainter;
+pub struct TilePainter {
+    size: u32,
+}
+
+impl Default for TilePainter {
+    fn default() -> Self {
+        Self { size: 1 }
+    }
+}
+
+impl TilePainter {
+    // The brush hangs off the cell by its bottom-left corner
+    fn brush_at(&self, ctx: &ToolContext, cell: TilePos) -> Result<Vec<(TilePos, TileProperties)>> {
+        let size = ctx.tilemap_size()?;
+        let block = ctx.brush_tiles()?;
+        let block_size = block.iter().fold(UVec2::ZERO, |block_size, (offset, _)| {
+            block_size.max(*offset + UVec2::ONE)
+        });
+
+        Ok((0..self.size)
+            .flat_map(|y| (0..self.size).map(move |x| UVec2::new(x, y) * block_size))
+            .flat_map(|origin| {
+                block
+                    .iter()
+                    .map(move |(offset, props)| (origin + *offset, *props))
+            })
+            .map(|(offset, props)| {
+                let pos = TilePos {
+                    x: cell.x + offset.x,
+                    y: cell.y + offset.y,
+                };
+
+                (pos, props)
+            })
+            .filter(|(pos, _)| pos.x < size.x && pos.y < size.y)
+            .collect())
+    }
+}
 
 impl Tool for TilePainter {
     fn name(&self) -> &'static str {
         "Brush"
     }
 
+    fn settings_ui(&mut self, ui: &mut egui::Ui) {
+        size_ui(ui, &mut self.size);
+    }
+
     fn viewport_ui(
         &mut self,
         ctx: &mut ToolContext,
         hovered_tile: TilePos,
-        ui: &mut egui::Ui,
+        _ui: &mut egui::Ui,
         painter: &Painter,
     ) -> Result<()> {
-        let display_rect = ctx.tile_rect(hovered_tile);
-        ctx.paint_tile(display_rect, painter)?;
-        painter.rect_stroke(
-            display_rect,
-            0.0,
+        let tiles = self.brush_at(ctx, hovered_tile)?;
+
+        for (pos, props) in &tiles {
+            ctx.paint_tile_with(*pos, props, painter)?;
+        }
+
+        let far_corner = tiles
+            .iter()
+            .fold(hovered_tile, |far_corner, (pos, _)| TilePos {
+                x: far_corner.x.max(pos.x),
+                y: far_corner.y.max(pos.y),
+            });
+        painter.add(egui::Shape::closed_line(
+            ctx.region_outline(hovered_tile, far_corner),
             egui::Stroke::new(1.0, egui::Color32::RED),
-        );
+        ));
 
-        if ui.input(|x| x.pointer.button_down(egui::PointerButton::Primary)) {
-            ctx.set_tile_properties(hovered_tile, *ctx.brush_state)?;
+        for cell in ctx.stroke_cells() {
+            for (pos, props) in self.brush_at(ctx, cell)? {
+                ctx.set_tile_properties(pos, props)?;
+            }
         }
 
         Ok(())
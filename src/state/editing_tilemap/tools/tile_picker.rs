@@ -3,7 +3,7 @@ use super::*;
 use bevy_ecs_tilemap::prelude::*;
 use bevy_editor_pls::egui;
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct TilePicker;
 
 impl Tool for TilePicker {
@@ -15,17 +15,15 @@ impl Tool for TilePicker {
         &mut self,
         ctx: &mut ToolContext,
         hovered_tile: TilePos,
-        ui: &mut egui::Ui,
+        _ui: &mut egui::Ui,
         painter: &Painter,
     ) -> Result<()> {
-        let display_rect = ctx.tile_rect(hovered_tile);
-        painter.rect_stroke(
-            display_rect,
-            0.0,
+        painter.add(egui::Shape::closed_line(
+            ctx.tile_outline(hovered_tile),
             egui::Stroke::new(1.0, egui::Color32::RED),
-        );
+        ));
 
-        if ui.input(|x| x.pointer.button_down(egui::PointerButton::Primary)) {
+        if ctx.stroke_active() {
             ctx.copy_tile_properties(hovered_tile)?;
         }
 
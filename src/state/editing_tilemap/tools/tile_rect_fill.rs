@@ -0,0 +1,81 @@
+use super::*;
+
+use bevy_ecs_tilemap::prelude::*;
+use bevy_editor_pls::egui;
+
+/// Fills a rectangle with the current brush. Holding `Shift` (the default of
+/// [`EditorHoldAction::SwapRectOutline`]) when releasing the mouse button
+/// swaps between filling and drawing the outline.
+#[derive(Debug, Default)]
+pub struct TileRectFill {
+    outline_only: bool,
+    start: Option<TilePos>,
+}
+
+impl Tool for TileRectFill {
+    fn name(&self) -> &'static str {
+        "Rectangle"
+    }
+
+    fn settings_ui(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.outline_only, "Outline only");
+    }
+
+    fn tilemap_changed(&mut self) {
+        self.start = None;
+    }
+
+    fn viewport_ui(
+        &mut self,
+        ctx: &mut ToolContext,
+        hovered_tile: TilePos,
+        ui: &mut egui::Ui,
+        painter: &Painter,
+    ) -> Result<()> {
+        let (pressed, released, down) = ui.input(|x| {
+            (
+                x.pointer.button_pressed(egui::PointerButton::Primary),
+                x.pointer.button_released(egui::PointerButton::Primary),
+                x.pointer.button_down(egui::PointerButton::Primary),
+            )
+        });
+        let outline_only = self.outline_only != ctx.held(EditorHoldAction::SwapRectOutline, ui);
+
+        if pressed {
+            self.start = Some(hovered_tile);
+        }
+
+        // The rectangle only gets filled if the button is released over the
+        // tilemap. Leaving it, or handing the pointer over to another tool
+        // for a moment, drops the rectangle.
+        match self.start {
+            Some(start) if released => {
+                self.start = None;
+
+                for pos in rect_tiles(start, hovered_tile, outline_only) {
+                    ctx.set_tile_properties(pos, ctx.brush())?;
+                }
+            }
+            Some(start) if down && ctx.stroke_active() => {
+                for pos in rect_tiles(start, hovered_tile, outline_only) {
+                    ctx.paint_tile(pos, painter)?;
+                }
+
+                painter.add(egui::Shape::closed_line(
+                    ctx.region_outline(start, hovered_tile),
+                    egui::Stroke::new(1.0, egui::Color32::RED),
+                ));
+            }
+            _ => {
+                self.start = None;
+
+                painter.add(egui::Shape::closed_line(
+                    ctx.tile_outline(hovered_tile),
+                    egui::Stroke::new(1.0, egui::Color32::RED),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
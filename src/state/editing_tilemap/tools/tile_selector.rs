@@ -0,0 +1,66 @@
+use super::*;
+
+use bevy_ecs_tilemap::prelude::*;
+use bevy_editor_pls::egui;
+
+/// Selects a rectangle of tiles, which can then be copied (`Ctrl+C`)
+/// or cut (`Ctrl+X`). `Ctrl+V` picks up the clipboard contents as a stamp,
+/// which gets placed by clicking. While holding the stamp, `R` and `Shift+R`
+/// rotate it, `H` and `V` flip it, and `Escape` or the right mouse button drop it.
+/// The keys are the defaults of [`crate::TilemapEditorKeymap`]. The shortcuts
+/// are handled by the editor itself, so they work with any tool.
+#[derive(Debug, Default)]
+pub struct TileSelector {
+    drag_start: Option<TilePos>,
+}
+
+impl Tool for TileSelector {
+    fn name(&self) -> &'static str {
+        "Select"
+    }
+
+    fn tilemap_changed(&mut self) {
+        self.drag_start = None;
+    }
+
+    fn viewport_ui(
+        &mut self,
+        ctx: &mut ToolContext,
+        hovered_tile: TilePos,
+        ui: &mut egui::Ui,
+        painter: &Painter,
+    ) -> Result<()> {
+        let (pressed, down) = ui.input(|x| {
+            (
+                x.pointer.button_pressed(egui::PointerButton::Primary),
+                x.pointer.button_down(egui::PointerButton::Primary),
+            )
+        });
+
+        if pressed {
+            self.drag_start = Some(hovered_tile);
+        }
+
+        if let Some(start) = self.drag_start {
+            ctx.set_selection(Some((start, hovered_tile)));
+
+            if !down {
+                self.drag_start = None;
+            }
+        }
+
+        if let Some((a, b)) = ctx.selection() {
+            painter.add(egui::Shape::closed_line(
+                ctx.region_outline(a, b),
+                egui::Stroke::new(1.0, egui::Color32::YELLOW),
+            ));
+        }
+
+        painter.add(egui::Shape::closed_line(
+            ctx.tile_outline(hovered_tile),
+            egui::Stroke::new(1.0, egui::Color32::RED),
+        ));
+
+        Ok(())
+    }
+}
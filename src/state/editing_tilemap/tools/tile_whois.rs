@@ -19,7 +19,7 @@ fn flip_flags_to_rotation(flip: TileFlip) -> &'static str {
     table[flip.d as usize | (flip.x as usize) << 1 | (flip.y as usize) << 2]
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct TileWhoIs;
 
 impl Tool for TileWhoIs {
@@ -34,12 +34,10 @@ impl Tool for TileWhoIs {
         ui: &mut egui::Ui,
         painter: &Painter,
     ) -> Result<()> {
-        let display_rect = ctx.tile_rect(hovered_tile);
-        painter.rect_stroke(
-            display_rect,
-            0.0,
+        painter.add(egui::Shape::closed_line(
+            ctx.tile_outline(hovered_tile),
             egui::Stroke::new(1.0, egui::Color32::RED),
-        );
+        ));
 
         let ent_and_props = ctx.get_tile_properties(hovered_tile)?;
         if let Some((ent, props)) = ent_and_props {
@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 use bevy::prelude::*;
 use bevy_editor_pls::egui::Ui;
 
@@ -6,6 +8,9 @@ use crate::{queries::EditorQueryStorage, EditorError};
 mod editing_tilemap;
 mod picking_tilemap;
 
+pub(crate) use editing_tilemap::EditorTools;
+pub use editing_tilemap::{TileProperties, Tool, ToolContext};
+
 enum Message {
     None,
     StartPickingTilemap,
@@ -15,11 +20,17 @@ enum Message {
 
 struct SharedStateData {
     query_storage: EditorQueryStorage,
+    history: editing_tilemap::EditHistory,
+    clipboard: Option<editing_tilemap::TileStamp>,
+    locked_layers: HashSet<Entity>,
+    // The tools outlive the editing state, so their settings stick around
+    tools: Vec<Box<dyn Tool>>,
+    current_tool: usize,
 }
 
 enum State {
-    Editing(editing_tilemap::StateData),
-    PickingTilemap(picking_tilemap::StateData),
+    Editing(Box<editing_tilemap::StateData>),
+    PickingTilemap(Box<picking_tilemap::StateData>),
 }
 
 pub struct EditorState {
@@ -28,35 +39,52 @@ pub struct EditorState {
 }
 
 impl EditorState {
-    fn state_switch(&mut self, state: State, world: &mut World) {
-        let old = std::mem::replace(&mut self.state, state);
+    // The old state gets cleaned up before the new one is created. Both of them
+    // might register the same images with egui, like when switching between
+    // layers with the same tileset, and the cleanup would unregister them.
+    fn state_switch(
+        &mut self,
+        world: &mut World,
+        new_state: impl FnOnce(&mut World, &mut SharedStateData) -> State,
+    ) {
+        let old = std::mem::replace(
+            &mut self.state,
+            State::PickingTilemap(Box::new(picking_tilemap::StateData::empty())),
+        );
 
         match old {
             State::Editing(x) => x.cleanup(world),
             State::PickingTilemap(x) => x.cleanup(world),
         }
+
+        self.state = new_state(world, &mut self.shared);
     }
 
     fn handle_message(&mut self, msg: Message, world: &mut World) {
+        let picking = |world: &mut World, shared: &mut SharedStateData| {
+            State::PickingTilemap(Box::new(picking_tilemap::StateData::new(world, shared)))
+        };
+
         match msg {
             Message::None => (),
-            Message::StartPickingTilemap => {
-                let state = picking_tilemap::StateData::new(world, &mut self.shared);
-
-                self.state_switch(State::PickingTilemap(state), world)
-            }
+            Message::StartPickingTilemap => self.state_switch(world, picking),
             Message::EditTilemap(e) => {
-                match editing_tilemap::StateData::new(e, world, &mut self.shared) {
-                    Ok(state) => self.state_switch(State::Editing(state), world),
-                    Err(e) => error!("Error: {e}"),
-                }
+                self.state_switch(
+                    world,
+                    |world, shared| match editing_tilemap::StateData::new(e, world, shared) {
+                        Ok(state) => State::Editing(Box::new(state)),
+                        Err(e) => {
+                            error!("Error: {e}");
+
+                            picking(world, shared)
+                        }
+                    },
+                )
             }
             // TODO show in the ui
             Message::ShowErrorAndExitEditing(err) => {
-                let state = picking_tilemap::StateData::new(world, &mut self.shared);
-
                 error!("The editor has closed due to the following error: {err}");
-                self.state_switch(State::PickingTilemap(state), world)
+                self.state_switch(world, picking)
             }
         }
     }
@@ -85,8 +113,13 @@ impl Default for EditorState {
         Self {
             shared: SharedStateData {
                 query_storage: EditorQueryStorage::new(),
+                history: editing_tilemap::EditHistory::default(),
+                clipboard: None,
+                locked_layers: HashSet::new(),
+                tools: Vec::new(),
+                current_tool: 0,
             },
-            state: State::PickingTilemap(picking_tilemap::StateData::empty()),
+            state: State::PickingTilemap(Box::new(picking_tilemap::StateData::empty())),
         }
     }
 }
@@ -1,21 +1,52 @@
+use bevy::asset::LoadState;
 use bevy::prelude::*;
-use bevy_ecs_tilemap::prelude::*;
-use bevy_editor_pls::egui;
+use bevy_editor_pls::{egui, egui_dock};
+
+use self::new_tilemap::{LoadingTilemap, NewTilemapForm};
+use self::tilemap_list::{Thumbnails, TilemapEntry};
 
 use super::{Message, SharedStateData};
+use crate::{EditorAction, TilemapEditorKeymap};
+
+mod new_tilemap;
+mod tilemap_list;
 
-pub(super) struct StateData {}
+pub(super) struct StateData {
+    new_tilemap: NewTilemapForm,
+    loading: Option<LoadingTilemap>,
+    filter: String,
+    thumbnails: Thumbnails,
+    // The tilemaps under the cursor, topmost first, and the one to pick
+    hovered: Vec<Entity>,
+    hovered_idx: usize,
+}
 
 impl StateData {
     pub fn empty() -> Self {
-        Self {}
+        Self {
+            new_tilemap: NewTilemapForm::default(),
+            loading: None,
+            filter: String::new(),
+            thumbnails: Thumbnails::default(),
+            hovered: Vec::new(),
+            hovered_idx: 0,
+        }
     }
 
     pub fn new(_world: &mut World, _shared_data: &mut SharedStateData) -> Self {
-        StateData {}
+        Self::empty()
+    }
+
+    pub fn cleanup(mut self, world: &mut World) {
+        self.thumbnails.release(world);
     }
 
-    pub fn cleanup(self, _world: &mut World) {}
+    fn pick(&mut self, world: &mut World, tilemap_entity: Entity) -> Message {
+        // The thumbnails aren't needed anymore
+        self.thumbnails.release(world);
+
+        Message::EditTilemap(tilemap_entity)
+    }
 
     pub fn ui(
         &mut self,
@@ -23,35 +54,158 @@ impl StateData {
         world: &mut World,
         ui: &mut egui::Ui,
     ) -> Message {
-        let queries = shared.query_storage.queries(world);
+        // The editor can't start until the atlas size is known
+        if let Some(loading) = &self.loading {
+            match loading.load_state(world) {
+                LoadState::Loaded => {
+                    let tilemap_entity = loading.tilemap_entity;
+
+                    self.loading = None;
+                    return self.pick(world, tilemap_entity);
+                }
+                LoadState::Failed => {
+                    error!("Failed to load the atlas of the new tilemap");
+                    world.entity_mut(loading.tilemap_entity).despawn_recursive();
+                    self.loading = None;
+                }
+                _ => {
+                    ui.label("Loading the atlas...");
+                    return Message::None;
+                }
+            }
+        }
+
+        ui.collapsing("New tilemap", |ui| {
+            if self.new_tilemap.ui(ui) {
+                let (tilemap_entity, image) = self.new_tilemap.spawn(world);
 
-        // TODO make naming more user-friendly
-        let pick = queries
+                self.loading = Some(LoadingTilemap {
+                    tilemap_entity,
+                    image,
+                });
+            }
+        });
+
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("Filter");
+            ui.text_edit_singleline(&mut self.filter);
+        });
+
+        let queries = shared.query_storage.queries(world);
+        let entries = queries
             .tilemap_query
             .iter(world)
-            .filter(|tilemap| matches!(tilemap.ty, TilemapType::Square)) // Ignore non-square tilemaps
-            .find(|tilemap| ui.button(self.name_tilemap(tilemap.name)).clicked());
+            .map(|tilemap| TilemapEntry::new(&tilemap, world))
+            .filter(|entry| entry.matches(&self.filter))
+            .collect::<Vec<_>>();
 
-        if let Some(tilemap) = pick {
-            return Message::EditTilemap(tilemap.entity);
-        }
+        let pick = egui::ScrollArea::vertical()
+            .show(ui, |ui| {
+                entries
+                    .iter()
+                    .filter(|entry| {
+                        let picked = entry.ui(ui, &mut self.thumbnails, world);
 
-        Message::None
+                        ui.separator();
+
+                        picked
+                    })
+                    .last()
+                    .map(|entry| entry.entity)
+            })
+            .inner;
+
+        match pick {
+            Some(tilemap_entity) => self.pick(world, tilemap_entity),
+            None => Message::None,
+        }
     }
 
+    /// Outlines the tilemaps under the cursor. Clicking picks one of them,
+    /// while [`EditorAction::CycleTilemaps`] cycles through them when they overlap.
     pub fn viewport_ui(
         &mut self,
-        _shared: &mut SharedStateData,
-        _world: &mut World,
-        _ui: &mut egui::Ui,
+        shared: &mut SharedStateData,
+        world: &mut World,
+        ui: &mut egui::Ui,
     ) -> Message {
-        Message::None
-    }
+        let queries = shared.query_storage.queries(world);
+
+        let viewport_rect = ui.clip_rect();
+        let mut clip_rect = viewport_rect;
+        clip_rect
+            .set_top(ui.min_rect().top() - egui_dock::Style::default().default_inner_margin.top);
+        let mut painter = ui.painter_at(clip_rect);
+        painter.set_layer_id(egui::LayerId::background());
+
+        let Some(cam) = queries.camera_query.iter(world)
+            .find(|x| x.is_active())
+        else {
+            return Message::None;
+        };
+        let Some(pointer) = ui
+            .input(|x| x.pointer.hover_pos())
+            .filter(|_| ui.ui_contains_pointer())
+        else {
+            return Message::None;
+        };
+
+        let mut hovered = queries
+            .tilemap_query
+            .iter(world)
+            .filter(|tilemap| TilemapEntry::new(tilemap, world).can_edit())
+            .filter_map(|tilemap| {
+                let points = cam.tilemap_points(viewport_rect, &tilemap)?;
+
+                points.hovered_tile(pointer)?;
 
-    fn name_tilemap<'a>(&'a mut self, name: Option<&'a Name>) -> &'a str {
-        match name {
-            None => "Unnamed tilemap",
-            Some(x) => x.as_str(),
+                Some((tilemap.entity, tilemap.transform.translation().z, points))
+            })
+            .collect::<Vec<_>>();
+        hovered.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let entities = hovered.iter().map(|x| x.0).collect::<Vec<_>>();
+        if entities != self.hovered {
+            self.hovered = entities;
+            self.hovered_idx = 0;
+        }
+        if self.hovered.is_empty() {
+            return Message::None;
+        }
+        let keymap = world.resource::<TilemapEditorKeymap>();
+        if keymap.pressed(EditorAction::CycleTilemaps, ui.ctx()) {
+            self.hovered_idx = (self.hovered_idx + 1) % self.hovered.len();
         }
+
+        for (idx, (_, _, points)) in hovered.iter().enumerate() {
+            let stroke = match idx == self.hovered_idx {
+                true => egui::Stroke::new(2.0, egui::Color32::YELLOW),
+                false => egui::Stroke::new(1.0, egui::Color32::GRAY),
+            };
+
+            painter.add(egui::Shape::closed_line(points.tilemap_outline(), stroke));
+        }
+
+        if self.hovered.len() > 1 {
+            let hint = match keymap.chord(EditorAction::CycleTilemaps) {
+                Some(chord) => format!(", {chord} to cycle"),
+                None => String::new(),
+            };
+
+            ui.label(format!(
+                "{} tilemaps overlap here{hint}",
+                self.hovered.len()
+            ));
+        }
+
+        if ui.input(|x| x.pointer.primary_clicked()) {
+            let tilemap_entity = self.hovered[self.hovered_idx];
+
+            return self.pick(world, tilemap_entity);
+        }
+
+        Message::None
     }
 }
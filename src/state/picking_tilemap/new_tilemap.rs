@@ -0,0 +1,109 @@
+use bevy::asset::LoadState;
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use bevy_editor_pls::egui;
+
+/// The form for spawning a brand new tilemap with an atlas tileset
+#[derive(Debug)]
+pub struct NewTilemapForm {
+    name: String,
+    image_path: String,
+    tile_size: Vec2,
+    grid_size: Vec2,
+    map_size: UVec2,
+    position: Vec2,
+    z: f32,
+}
+
+impl Default for NewTilemapForm {
+    fn default() -> Self {
+        Self {
+            name: "New tilemap".to_owned(),
+            image_path: String::new(),
+            tile_size: Vec2::splat(16.0),
+            grid_size: Vec2::splat(16.0),
+            map_size: UVec2::splat(32),
+            position: Vec2::ZERO,
+            z: 0.0,
+        }
+    }
+}
+
+fn vec2_ui(ui: &mut egui::Ui, label: &str, value: &mut Vec2, min: f32) {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        ui.add(egui::DragValue::new(&mut value.x).clamp_range(min..=f32::MAX));
+        ui.add(egui::DragValue::new(&mut value.y).clamp_range(min..=f32::MAX));
+    });
+}
+
+impl NewTilemapForm {
+    /// Returns `true` when the user asks to spawn the tilemap
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        ui.horizontal(|ui| {
+            ui.label("Name");
+            ui.text_edit_singleline(&mut self.name);
+        });
+        ui.horizontal(|ui| {
+            ui.label("Atlas image");
+            ui.text_edit_singleline(&mut self.image_path);
+        });
+
+        vec2_ui(ui, "Tile size", &mut self.tile_size, 1.0);
+        vec2_ui(ui, "Grid size", &mut self.grid_size, 1.0);
+        ui.horizontal(|ui| {
+            ui.label("Map size");
+            ui.add(egui::DragValue::new(&mut self.map_size.x).clamp_range(1..=u16::MAX as u32));
+            ui.add(egui::DragValue::new(&mut self.map_size.y).clamp_range(1..=u16::MAX as u32));
+        });
+        vec2_ui(ui, "Position", &mut self.position, f32::MIN);
+        ui.horizontal(|ui| {
+            ui.label("Z");
+            ui.add(egui::DragValue::new(&mut self.z));
+        });
+
+        ui.add_enabled(!self.image_path.is_empty(), egui::Button::new("Spawn"))
+            .clicked()
+    }
+
+    /// Spawns the tilemap. The returned image handle is the atlas, which
+    /// has to finish loading before the tilemap can be edited.
+    pub fn spawn(&self, world: &mut World) -> (Entity, Handle<Image>) {
+        let image = world
+            .resource::<AssetServer>()
+            .load::<Image, _>(self.image_path.as_str());
+        let size = TilemapSize {
+            x: self.map_size.x,
+            y: self.map_size.y,
+        };
+        let tilemap_entity = world
+            .spawn((
+                TilemapBundle {
+                    grid_size: self.grid_size.into(),
+                    size,
+                    storage: TileStorage::empty(size),
+                    texture: TilemapTexture::Single(image.clone()),
+                    tile_size: self.tile_size.into(),
+                    transform: Transform::from_translation(self.position.extend(self.z)),
+                    ..default()
+                },
+                Name::new(self.name.clone()),
+            ))
+            .id();
+
+        (tilemap_entity, image)
+    }
+}
+
+/// A freshly spawned tilemap, whose atlas is still loading
+#[derive(Debug)]
+pub struct LoadingTilemap {
+    pub tilemap_entity: Entity,
+    pub image: Handle<Image>,
+}
+
+impl LoadingTilemap {
+    pub fn load_state(&self, world: &World) -> LoadState {
+        world.resource::<AssetServer>().get_load_state(&self.image)
+    }
+}
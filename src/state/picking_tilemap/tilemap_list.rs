@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use bevy_editor_pls::egui;
+use bevy_egui::EguiUserTextures;
+
+use crate::queries::TilemapQueryReadOnlyItem;
+
+// The thumbnails get fit into a square of this size
+const THUMBNAIL_SIZE: f32 = 48.0;
+
+/// What the picker knows about a tilemap
+pub struct TilemapEntry {
+    pub entity: Entity,
+    name: String,
+    size: TilemapSize,
+    tile_size: TilemapTileSize,
+    texture_path: String,
+    // The image to show the thumbnail of. Texture containers don't get one.
+    preview: Option<Handle<Image>>,
+    // Why the tilemap can't be edited
+    problem: Option<&'static str>,
+}
+
+impl TilemapEntry {
+    pub fn new(tilemap: &TilemapQueryReadOnlyItem, world: &World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
+        let images = world.resource::<Assets<Image>>();
+        let path = |handle: &Handle<Image>| {
+            asset_server
+                .get_handle_path(handle)
+                .map(|x| x.path().to_string_lossy().into_owned())
+                .unwrap_or_else(|| "<no path>".to_owned())
+        };
+
+        let (texture_path, preview) = match tilemap.texture {
+            TilemapTexture::Single(x) => (path(x), Some(x.clone_weak())),
+            TilemapTexture::Vector(xs) => match xs.first() {
+                Some(x) => (
+                    format!("{} (+{} more)", path(x), xs.len() - 1),
+                    Some(x.clone_weak()),
+                ),
+                None => ("<no images>".to_owned(), None),
+            },
+            TilemapTexture::TextureContainer(x) => (path(x), None),
+        };
+        let problem = if !tilemap
+            .texture
+            .image_handles()
+            .iter()
+            .all(|x| images.contains(*x))
+        {
+            Some("The tileset images haven't been loaded")
+        } else if tilemap.storage.size != *tilemap.size {
+            Some("The tile storage size doesn't match the tilemap size")
+        } else {
+            None
+        };
+
+        Self {
+            entity: tilemap.entity,
+            name: tilemap
+                .name
+                .map_or("Unnamed tilemap", |x| x.as_str())
+                .to_owned(),
+            size: *tilemap.size,
+            tile_size: *tilemap.tile_size,
+            texture_path,
+            preview,
+            problem,
+        }
+    }
+
+    pub fn can_edit(&self) -> bool {
+        self.problem.is_none()
+    }
+
+    pub fn matches(&self, filter: &str) -> bool {
+        let filter = filter.to_lowercase();
+
+        [
+            &self.name,
+            &format!("{:?}", self.entity),
+            &self.texture_path,
+        ]
+        .iter()
+        .any(|x| x.to_lowercase().contains(&filter))
+    }
+
+    /// Returns `true` if the user has picked the tilemap
+    pub fn ui(&self, ui: &mut egui::Ui, thumbnails: &mut Thumbnails, world: &mut World) -> bool {
+        ui.horizontal(|ui| {
+            let (rect, _) =
+                ui.allocate_exact_size(egui::Vec2::splat(THUMBNAIL_SIZE), egui::Sense::hover());
+
+            if let Some((texture, size)) =
+                self.preview.as_ref().and_then(|x| thumbnails.get(x, world))
+            {
+                let scale = THUMBNAIL_SIZE / size.max_element();
+                let uv = egui::Rect::from_min_max(egui::Pos2::ZERO, egui::pos2(1.0, 1.0));
+
+                ui.painter().image(
+                    texture,
+                    egui::Rect::from_center_size(
+                        rect.center(),
+                        egui::vec2(size.x * scale, size.y * scale),
+                    ),
+                    uv,
+                    egui::Color32::WHITE,
+                );
+            }
+
+            ui.vertical(|ui| {
+                let picked = ui
+                    .add_enabled(self.problem.is_none(), egui::Button::new(&self.name))
+                    .on_disabled_hover_text(self.problem.unwrap_or_default())
+                    .clicked();
+
+                ui.label(format!(
+                    "{:?}, {}x{} tiles of {}x{}",
+                    self.entity, self.size.x, self.size.y, self.tile_size.x, self.tile_size.y
+                ));
+                ui.label(&self.texture_path);
+
+                if let Some(problem) = self.problem {
+                    ui.colored_label(egui::Color32::RED, problem);
+                }
+
+                picked
+            })
+            .inner
+        })
+        .inner
+    }
+}
+
+/// The images registered with egui for the thumbnails
+#[derive(Default)]
+pub struct Thumbnails(HashMap<Handle<Image>, egui::TextureId>);
+
+impl Thumbnails {
+    fn get(&mut self, image: &Handle<Image>, world: &mut World) -> Option<(egui::TextureId, Vec2)> {
+        let size = world.resource::<Assets<Image>>().get(image)?.size();
+        let texture = *self.0.entry(image.clone_weak()).or_insert_with(|| {
+            world
+                .resource_mut::<EguiUserTextures>()
+                .add_image(image.clone_weak())
+        });
+
+        Some((texture, size))
+    }
+
+    /// Unregisters all the images
+    pub fn release(&mut self, world: &mut World) {
+        let mut egui_textures = world.resource_mut::<EguiUserTextures>();
+
+        for (image, _) in self.0.drain() {
+            egui_textures.remove_image(&image);
+        }
+    }
+}
@@ -34,6 +34,16 @@ impl TileData {
             .for_each(|(refl, _)| refl.remove(entity))
     }
 
+    pub fn capture(&self, src: EntityRef) -> Vec<(ReflectComponent, Box<dyn Reflect>)> {
+        self.components
+            .values()
+            .filter_map(|(refl, _)| {
+                refl.reflect(src)
+                    .map(|value| (refl.clone(), value.clone_value()))
+            })
+            .collect()
+    }
+
     pub fn values_mut(&'_ mut self) -> impl Iterator<Item = &'_ mut dyn Reflect> {
         self.components.values_mut().map(|x| x.1.as_mut())
     }
@@ -66,9 +76,13 @@ impl<'a> EditorTileDataRegistryLock<'a> {
         &'_ mut self,
         tileset_info: TilemapTexture,
     ) -> &'_ mut HashMap<u32, TileData> {
-        self.0.map.entry(tileset_info).or_default()
+        // Don't let the registry keep the images alive
+        self.0.map.entry(tileset_info.clone_weak()).or_default()
     }
 
+    /// Gives access to the tile data of the tile with texture `tile_id`.
+    /// For [`TilemapTexture::Vector`] tilesets, the tile id is
+    /// the index of the tile's image in the vector.
     pub fn edit_tile_data<'b>(
         &'b mut self,
         registry: &'b AppTypeRegistry,